@@ -1,6 +1,9 @@
 use super::{entity::{self}, player};
 use bevy::prelude::*;
-use mooshroom::{proto::connection::MooshroomConnection, server::play::PlayStage};
+use mooshroom::{
+    proto::connection::{Housekeeping, MooshroomConnection},
+    server::play::PlayStage,
+};
 use std::{net::TcpStream, sync::mpsc};
 
 pub struct MinecraftConnection {
@@ -17,6 +20,7 @@ fn run_server(tx: mpsc::Sender<PlayStage>) -> mooshroom::core::error::Result<()>
 
     info!("Connected! handshaking...");
 
+    c.set_housekeeping(Housekeeping::enabled());
     c.handshake_offline()?;
 
     info!("reading play packets...");
@@ -25,10 +29,6 @@ fn run_server(tx: mpsc::Sender<PlayStage>) -> mooshroom::core::error::Result<()>
         let packet = c.next_play_packet()?;
         //info!("{:?}", packet);
         match &packet {
-            PlayStage::KeepAlive(id) => {
-                println!("Sent keepalive");
-                c.respond_to_keep_alive(id.0)?;
-            }
             PlayStage::SetHealth(p) => {
                 println!("{:#?}", p);
                 if p.health <= 0. {
@@ -21,6 +21,8 @@ pub trait MooshroomCollection<const PV: Protocal>: Sized {
     fn read_one_of(id: VarInt, reader: &mut impl io::Read) -> Result<Self>;
     fn write_one_of(&self, writer: &mut impl io::Write) -> Result<()>;
     fn variant_id(&self) -> VarInt;
+    /// The packet held by the variant, unwrapping nested collections.
+    fn variant_any(&self) -> &dyn std::any::Any;
 }
 
 pub trait MooshroomUpdatable {
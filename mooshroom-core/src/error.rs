@@ -20,12 +20,38 @@ pub enum MooshroomError {
     NotInCollection(i32),
     #[error("Failed to parse nbt tag of type {0}")]
     InvalidNbtTag(u8),
+    #[error("Invalid length {0}")]
+    InvalidLength(i32),
     #[error("Invalid enum variant {0}")]
     InvalidEnumVariant(i32),
     #[error("Could not find value for id {0} ")]
     InvalidId(String),
     #[error("No Id found for value")]
     NoId,
+    #[error("Connection closed by peer")]
+    ConnectionClosed,
+    #[error("Timed out waiting on the connection")]
+    TimedOut,
+    #[error("Invalid legacy ping. {0}")]
+    InvalidLegacyPing(&'static str),
+    #[error("Shared secret must be 16 bytes")]
+    InvalidSharedSecret,
+    #[error("Encryption failed. {0}")]
+    Encryption(String),
+    #[error("Invalid favicon. {0}")]
+    InvalidFavicon(String),
+    #[error("Disconnected by server: {reason}")]
+    Disconnected { reason: String },
+    #[error("Session authentication failed. {0}")]
+    AuthenticationFailed(String),
+    #[error("Not in the play state")]
+    NotInPlay,
+    #[error("Invalid profile key. {0}")]
+    InvalidProfileKey(String),
+    #[error("Invalid recording. {0}")]
+    InvalidRecording(String),
+    #[error("Pong {received} does not match ping {sent}")]
+    PongMismatch { sent: u64, received: u64 },
 
     #[cfg(feature = "uuid")]
     #[error("Invalid uuid. {0}")]
@@ -79,16 +79,27 @@ impl<const PV: usize> MooshroomWritable<PV> for u8 {
     }
 }
 
+/// Reads `len` bytes without trusting `len` for the allocation, so a bogus
+/// length fails at the end of the data instead of allocating it up front.
+pub fn read_bytes(reader: &mut impl std::io::Read, len: i32) -> crate::error::Result<Vec<u8>> {
+    use std::io::Read;
+
+    if len < 0 {
+        return Err(MooshroomError::InvalidLength(len));
+    }
+    let mut buffer = Vec::new();
+    reader.by_ref().take(len as u64).read_to_end(&mut buffer)?;
+    if buffer.len() != len as usize {
+        return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into());
+    }
+    Ok(buffer)
+}
+
 impl<const PV: usize> MooshroomReadable<PV> for String {
     fn read(reader: &mut impl std::io::Read) -> crate::error::Result<Self> {
         let len = <VarInt as MooshroomReadable<PV>>::read(reader)?;
-
-        let s = {
-            let mut buffer: Vec<u8> = vec![0; len.0 as usize];
-            reader.read_exact(&mut buffer)?;
-            String::from_utf8(buffer).map_err(MooshroomError::InvalidString)?
-        };
-        Ok(s)
+        let buffer = read_bytes(reader, len.0)?;
+        String::from_utf8(buffer).map_err(MooshroomError::InvalidString)
     }
 }
 
@@ -105,9 +116,14 @@ where
     T: MooshroomReadable<PV>,
 {
     fn read(reader: &mut impl std::io::Read) -> crate::error::Result<Self> {
-        let len = <VarInt as MooshroomReadable<PV>>::read(reader)?.0 as usize;
+        let len = <VarInt as MooshroomReadable<PV>>::read(reader)?.0;
+        if len < 0 {
+            return Err(MooshroomError::InvalidLength(len));
+        }
 
-        let mut buffer = Vec::with_capacity(len);
+        // Only a bounded amount is reserved up front, the length comes from
+        // the peer.
+        let mut buffer = Vec::with_capacity((len as usize).min(1024));
         for _ in 0..len {
             buffer.push(T::read(reader)?);
         }
@@ -128,6 +144,31 @@ where
     }
 }
 
+/// Bytes that run to the end of the packet, with no length prefix.
+#[derive(Default, Clone, Debug, PartialEq, Eq)]
+pub struct RemainingBytes(pub Vec<u8>);
+
+impl<const PV: usize> MooshroomReadable<PV> for RemainingBytes {
+    fn read(reader: &mut impl std::io::Read) -> crate::error::Result<Self> {
+        let mut buffer = Vec::new();
+        reader.read_to_end(&mut buffer)?;
+        Ok(Self(buffer))
+    }
+}
+
+impl<const PV: usize> MooshroomWritable<PV> for RemainingBytes {
+    fn write(&self, writer: &mut impl std::io::Write) -> crate::error::Result<()> {
+        writer.write_all(&self.0)?;
+        Ok(())
+    }
+}
+
+impl From<Vec<u8>> for RemainingBytes {
+    fn from(v: Vec<u8>) -> Self {
+        Self(v)
+    }
+}
+
 impl<const PV: usize, T, const N: usize> MooshroomReadable<PV> for [T; N]
 where
     T: MooshroomReadable<PV> + Sized,
@@ -171,8 +212,8 @@ impl<const PV: usize> MooshroomWritable<PV> for uuid::Uuid {
     }
 }
 
-const I26_MASK: i32 = 0b1111111111_1111111111_111111;
-const I12_MASK: i16 = 0b1111111111_11;
+const I26_MASK: i64 = 0x3FF_FFFF;
+const I12_MASK: i64 = 0xFFF;
 
 #[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
 pub struct Position {
@@ -183,23 +224,22 @@ pub struct Position {
 
 impl<const PV: usize> MooshroomReadable<PV> for Position {
     fn read(reader: &mut impl std::io::Read) -> crate::error::Result<Self> {
-        let base = u64::read_proto::<PV>(reader)?;
+        // Shifting the signed value back down sign extends each field.
+        let base = u64::read_proto::<PV>(reader)? as i64;
         Ok(Self {
-            x: (base >> 38) as i32 & I26_MASK,
-            z: (base >> 12) as i32 & I26_MASK,
-            y: base as i16 & I12_MASK,
+            x: (base >> 38) as i32,
+            z: (base << 26 >> 38) as i32,
+            y: (base << 52 >> 52) as i16,
         })
     }
 }
 
 impl<const PV: usize> MooshroomWritable<PV> for Position {
     fn write(&self, writer: &mut impl std::io::Write) -> crate::error::Result<()> {
-        let mut buffer = [0; 8];
-        buffer[..26].copy_from_slice(&(self.x & I26_MASK).to_be_bytes()[6..]);
-        buffer[26..26 + 26].copy_from_slice(&(self.z & I26_MASK).to_be_bytes()[6..]);
-        buffer[26 + 26..].copy_from_slice(&(self.y & I12_MASK).to_be_bytes()[4..]);
-        writer.write_all(&buffer)?;
-        Ok(())
+        let base = ((self.x as i64 & I26_MASK) << 38)
+            | ((self.z as i64 & I26_MASK) << 12)
+            | (self.y as i64 & I12_MASK);
+        (base as u64).write_proto::<PV>(writer)
     }
 }
 
@@ -272,3 +312,56 @@ impl<const PV: usize> MooshroomWritable<PV> for () {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::DEFAULT_PROTOCAL_VERSION;
+
+    #[test]
+    fn position_round_trip() {
+        let pos = Position {
+            x: -33554432,
+            z: 33554431,
+            y: -64,
+        };
+        let mut buf = Vec::new();
+        pos.write_proto::<DEFAULT_PROTOCAL_VERSION>(&mut buf)
+            .unwrap();
+        assert_eq!(
+            Position::read_proto::<DEFAULT_PROTOCAL_VERSION>(&mut &buf[..]).unwrap(),
+            pos
+        );
+
+        // Example from the protocol docs.
+        let buf = 0x4607_632c_15b4_833f_u64;
+        let pos = Position::read_proto::<DEFAULT_PROTOCAL_VERSION>(&mut &buf.to_be_bytes()[..]);
+        assert_eq!(
+            pos.unwrap(),
+            Position {
+                x: 18357644,
+                z: -20882616,
+                y: 831,
+            }
+        );
+    }
+
+    #[test]
+    fn bogus_lengths() {
+        const PV: usize = DEFAULT_PROTOCAL_VERSION;
+        // A length of i32::MAX followed by nothing.
+        let huge = [0xff, 0xff, 0xff, 0xff, 0x07];
+        assert!(Vec::<u64>::read_proto::<PV>(&mut &huge[..]).is_err());
+        assert!(String::read_proto::<PV>(&mut &huge[..]).is_err());
+
+        let negative = [0xff, 0xff, 0xff, 0xff, 0x0f];
+        assert!(matches!(
+            Vec::<u8>::read_proto::<PV>(&mut &negative[..]),
+            Err(MooshroomError::InvalidLength(-1))
+        ));
+        assert!(matches!(
+            String::read_proto::<PV>(&mut &negative[..]),
+            Err(MooshroomError::InvalidLength(-1))
+        ));
+    }
+}
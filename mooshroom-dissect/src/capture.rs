@@ -0,0 +1,287 @@
+use std::{
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    time::Duration,
+};
+
+const PCAP_MICROS: u32 = 0xa1b2_c3d4;
+const PCAP_NANOS: u32 = 0xa1b2_3c4d;
+const PCAPNG_SECTION: u32 = 0x0a0d_0d0a;
+const PCAPNG_BYTE_ORDER: u32 = 0x1a2b_3c4d;
+
+/// Link types we can find IP packets in.
+const LINK_NULL: u16 = 0;
+const LINK_ETHERNET: u16 = 1;
+const LINK_RAW: u16 = 101;
+const LINK_LINUX_SLL: u16 = 113;
+const LINK_LINUX_SLL2: u16 = 276;
+
+/// Whether the file starts like a pcap or pcapng capture.
+pub fn is_capture(bytes: &[u8]) -> bool {
+    let magic = match bytes.get(..4) {
+        Some(m) => [m[0], m[1], m[2], m[3]],
+        None => return false,
+    };
+    [PCAP_MICROS, PCAP_NANOS]
+        .iter()
+        .any(|m| m.to_le_bytes() == magic || m.to_be_bytes() == magic)
+        || u32::from_le_bytes(magic) == PCAPNG_SECTION
+}
+
+/// A link layer frame from a capture.
+pub struct Captured<'a> {
+    /// Since the unix epoch.
+    pub time: Duration,
+    pub link_type: u16,
+    pub data: &'a [u8],
+}
+
+/// A TCP segment.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Segment<'a> {
+    pub time: Duration,
+    pub src: SocketAddr,
+    pub dst: SocketAddr,
+    pub seq: u32,
+    pub syn: bool,
+    pub ack: bool,
+    pub payload: &'a [u8],
+}
+
+/// Reads the frames of a pcap or pcapng capture.
+pub fn read_capture(bytes: &[u8]) -> Result<Vec<Captured<'_>>, String> {
+    if bytes.len() >= 4
+        && u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) == PCAPNG_SECTION
+    {
+        read_pcapng(bytes)
+    } else {
+        read_pcap(bytes)
+    }
+}
+
+/// Reads integers in the byte order of the capture.
+#[derive(Clone, Copy)]
+struct Endian(bool);
+
+impl Endian {
+    fn u16(self, b: &[u8], at: usize) -> Option<u16> {
+        let b = [*b.get(at)?, *b.get(at + 1)?];
+        Some(if self.0 {
+            u16::from_be_bytes(b)
+        } else {
+            u16::from_le_bytes(b)
+        })
+    }
+
+    fn u32(self, b: &[u8], at: usize) -> Option<u32> {
+        let b: [u8; 4] = b.get(at..at + 4)?.try_into().ok()?;
+        Some(if self.0 {
+            u32::from_be_bytes(b)
+        } else {
+            u32::from_le_bytes(b)
+        })
+    }
+}
+
+fn truncated() -> String {
+    "the capture is truncated".into()
+}
+
+fn read_pcap(bytes: &[u8]) -> Result<Vec<Captured<'_>>, String> {
+    let magic = Endian(false).u32(bytes, 0).ok_or_else(truncated)?;
+    let (endian, nanos) = if magic == PCAP_MICROS || magic == PCAP_NANOS {
+        (Endian(false), magic == PCAP_NANOS)
+    } else {
+        let magic = Endian(true).u32(bytes, 0).ok_or_else(truncated)?;
+        if magic != PCAP_MICROS && magic != PCAP_NANOS {
+            return Err("not a pcap capture".into());
+        }
+        (Endian(true), magic == PCAP_NANOS)
+    };
+    // The link type is 32 bits in the header but only the low 16 are used.
+    let link_type = endian.u32(bytes, 20).ok_or_else(truncated)? as u16;
+
+    let mut frames = Vec::new();
+    let mut at = 24;
+    while at < bytes.len() {
+        let header = |i| endian.u32(bytes, at + i).ok_or_else(truncated);
+        let (secs, frac, len) = (header(0)?, header(4)?, header(8)? as usize);
+        let data = bytes.get(at + 16..at + 16 + len).ok_or_else(truncated)?;
+        let frac = if nanos {
+            Duration::from_nanos(frac as u64)
+        } else {
+            Duration::from_micros(frac as u64)
+        };
+        frames.push(Captured {
+            time: Duration::from_secs(secs as u64) + frac,
+            link_type,
+            data,
+        });
+        at += 16 + len;
+    }
+    Ok(frames)
+}
+
+struct Interface {
+    link_type: u16,
+    /// Timestamp units per second.
+    resolution: u64,
+}
+
+fn read_pcapng(bytes: &[u8]) -> Result<Vec<Captured<'_>>, String> {
+    let mut frames = Vec::new();
+    let mut interfaces = Vec::new();
+    let mut endian = Endian(false);
+    let mut at = 0;
+    while at < bytes.len() {
+        let block_type = endian.u32(bytes, at).ok_or_else(truncated)?;
+        if block_type == PCAPNG_SECTION {
+            endian = match Endian(false).u32(bytes, at + 8) {
+                Some(PCAPNG_BYTE_ORDER) => Endian(false),
+                Some(_) => Endian(true),
+                None => return Err(truncated()),
+            };
+            // Interface ids are numbered per section.
+            interfaces.clear();
+        }
+        let len = endian.u32(bytes, at + 4).ok_or_else(truncated)? as usize;
+        if len < 12 {
+            return Err(format!("invalid pcapng block length {len}"));
+        }
+        let block = bytes.get(at + 8..at + len - 4).ok_or_else(truncated)?;
+        match block_type {
+            // Interface description
+            1 => interfaces.push(Interface {
+                link_type: endian.u16(block, 0).ok_or_else(truncated)?,
+                resolution: read_resolution(endian, block.get(8..).unwrap_or_default()),
+            }),
+            // Enhanced packet
+            6 => {
+                let field = |i| endian.u32(block, i).ok_or_else(truncated);
+                let interface = interfaces
+                    .get(field(0)? as usize)
+                    .ok_or("a packet refers to an unknown interface")?;
+                let ticks = (field(4)? as u64) << 32 | field(8)? as u64;
+                let len = field(12)? as usize;
+                frames.push(Captured {
+                    time: ticks_to_duration(ticks, interface.resolution),
+                    link_type: interface.link_type,
+                    data: block.get(20..20 + len).ok_or_else(truncated)?,
+                });
+            }
+            // Simple packet, which has no timestamp.
+            3 => {
+                let interface = interfaces
+                    .first()
+                    .ok_or("a packet refers to an unknown interface")?;
+                let len = endian.u32(block, 0).ok_or_else(truncated)? as usize;
+                let data = &block[4..];
+                frames.push(Captured {
+                    time: Duration::ZERO,
+                    link_type: interface.link_type,
+                    data: &data[..len.min(data.len())],
+                });
+            }
+            _ => {}
+        }
+        at += len;
+    }
+    Ok(frames)
+}
+
+/// Reads the `if_tsresol` option, microseconds if it is missing.
+fn read_resolution(endian: Endian, mut options: &[u8]) -> u64 {
+    while let (Some(code), Some(len)) = (endian.u16(options, 0), endian.u16(options, 2)) {
+        let len = len as usize;
+        if code == 0 {
+            break;
+        }
+        if let (9, 1, Some(&value)) = (code, len, options.get(4)) {
+            let exponent = (value & 0x7f) as u32;
+            return if value & 0x80 == 0 {
+                10u64.checked_pow(exponent)
+            } else {
+                2u64.checked_pow(exponent)
+            }
+            .unwrap_or(u64::MAX);
+        }
+        let padded = 4 + len.div_ceil(4) * 4;
+        options = options.get(padded..).unwrap_or_default();
+    }
+    1_000_000
+}
+
+fn ticks_to_duration(ticks: u64, resolution: u64) -> Duration {
+    let secs = ticks / resolution;
+    let nanos = (ticks % resolution) as u128 * 1_000_000_000 / resolution as u128;
+    Duration::new(secs, nanos as u32)
+}
+
+/// Finds the TCP segment in a frame, if it holds one.
+pub fn tcp_segment<'a>(frame: &Captured<'a>) -> Option<Segment<'a>> {
+    let data = frame.data;
+    let ip = match frame.link_type {
+        LINK_NULL => data.get(4..)?,
+        LINK_RAW => data,
+        LINK_ETHERNET => {
+            let mut at = 12;
+            let mut ether_type = u16::from_be_bytes([*data.get(at)?, *data.get(at + 1)?]);
+            // Skip VLAN tags.
+            while ether_type == 0x8100 || ether_type == 0x88a8 {
+                at += 4;
+                ether_type = u16::from_be_bytes([*data.get(at)?, *data.get(at + 1)?]);
+            }
+            data.get(at + 2..)?
+        }
+        LINK_LINUX_SLL => data.get(16..)?,
+        LINK_LINUX_SLL2 => data.get(20..)?,
+        _ => return None,
+    };
+
+    let (src, dst, tcp) = match ip.first()? >> 4 {
+        4 => {
+            let header_len = (ip[0] & 0x0f) as usize * 4;
+            let total_len = u16::from_be_bytes([*ip.get(2)?, *ip.get(3)?]) as usize;
+            let fragment = u16::from_be_bytes([*ip.get(6)?, *ip.get(7)?]);
+            // Fragments are rare enough on a loopback or LAN to not bother
+            // reassembling them.
+            if *ip.get(9)? != 6 || fragment & 0x3fff != 0 {
+                return None;
+            }
+            let src: [u8; 4] = ip.get(12..16)?.try_into().ok()?;
+            let dst: [u8; 4] = ip.get(16..20)?.try_into().ok()?;
+            (
+                IpAddr::from(Ipv4Addr::from(src)),
+                IpAddr::from(Ipv4Addr::from(dst)),
+                // The total length drops any ethernet padding.
+                ip.get(header_len..total_len.min(ip.len()))?,
+            )
+        }
+        6 => {
+            let payload_len = u16::from_be_bytes([*ip.get(4)?, *ip.get(5)?]) as usize;
+            if *ip.get(6)? != 6 {
+                return None;
+            }
+            let src: [u8; 16] = ip.get(8..24)?.try_into().ok()?;
+            let dst: [u8; 16] = ip.get(24..40)?.try_into().ok()?;
+            (
+                IpAddr::from(Ipv6Addr::from(src)),
+                IpAddr::from(Ipv6Addr::from(dst)),
+                ip.get(40..(40 + payload_len).min(ip.len()))?,
+            )
+        }
+        _ => return None,
+    };
+
+    let port = |at: usize| Some(u16::from_be_bytes([*tcp.get(at)?, *tcp.get(at + 1)?]));
+    let header_len = (*tcp.get(12)? >> 4) as usize * 4;
+    let flags = *tcp.get(13)?;
+    Some(Segment {
+        time: frame.time,
+        src: SocketAddr::new(src, port(0)?),
+        dst: SocketAddr::new(dst, port(2)?),
+        seq: u32::from_be_bytes(tcp.get(4..8)?.try_into().ok()?),
+        syn: flags & 0x02 != 0,
+        ack: flags & 0x10 != 0,
+        payload: tcp.get(header_len..)?,
+    })
+}
@@ -0,0 +1,233 @@
+use std::{collections::BTreeMap, fmt::Write as _, io::Write, time::Duration};
+
+use mooshroom::{
+    core::io::DEFAULT_PROTOCAL_VERSION,
+    proto::{
+        codec::{MooshroomCodec, RawPacket},
+        decode::{decode, hex, Bound, StateTracker},
+        recording::ProtocolState,
+    },
+};
+
+const PV: usize = DEFAULT_PROTOCAL_VERSION;
+
+/// Follows one connection, decoding packets as they are completed.
+pub struct Dissector {
+    connection: usize,
+    tracker: StateTracker,
+    serverbound: MooshroomCodec<PV>,
+    clientbound: MooshroomCodec<PV>,
+    /// Set once the rest of the connection can't be read.
+    stopped: bool,
+}
+
+impl Dissector {
+    pub fn new(report: &mut Report, state: ProtocolState, compression: Option<i32>) -> Self {
+        let mut serverbound = MooshroomCodec::new();
+        let mut clientbound = MooshroomCodec::new();
+        if let Some(threshold) = compression {
+            serverbound.set_compression(threshold);
+            clientbound.set_compression(threshold);
+        }
+        Self {
+            connection: report.next_connection(),
+            tracker: StateTracker::new(state),
+            serverbound,
+            clientbound,
+            stopped: false,
+        }
+    }
+
+    /// Adds bytes from the stream and decodes the packets they complete.
+    pub fn feed(&mut self, time: Duration, bound: Bound, bytes: &[u8], report: &mut Report) {
+        if self.stopped {
+            return;
+        }
+        self.codec(bound).add_bytes(bytes);
+        loop {
+            match self.codec(bound).read_raw() {
+                Ok(Some(raw)) => self.packet(time, bound, raw, report),
+                Ok(None) => break,
+                Err(e) => {
+                    self.stop(
+                        report,
+                        format!("failed to frame a {} packet: {e}", bound.name()),
+                    );
+                }
+            }
+            if self.stopped {
+                break;
+            }
+        }
+    }
+
+    fn codec(&mut self, bound: Bound) -> &mut MooshroomCodec<PV> {
+        match bound {
+            Bound::Serverbound => &mut self.serverbound,
+            Bound::Clientbound => &mut self.clientbound,
+        }
+    }
+
+    fn packet(&mut self, time: Duration, bound: Bound, raw: RawPacket, report: &mut Report) {
+        report.packet(self.connection, time, bound, self.tracker.state(), &raw);
+        if let Err(e) = self.track(bound, &raw) {
+            self.stop(report, e);
+        }
+    }
+
+    fn stop(&mut self, report: &mut Report, reason: String) {
+        self.stopped = true;
+        report
+            .stopped
+            .push(format!("#{}: {reason}", self.connection));
+    }
+
+    /// Follows the state and compression of the connection.
+    fn track(&mut self, bound: Bound, raw: &RawPacket) -> Result<(), String> {
+        let compression = self
+            .tracker
+            .observe(bound, raw)
+            .map_err(|e| e.to_string())?;
+        if let Some(threshold) = compression {
+            self.serverbound.set_compression(threshold);
+            self.clientbound.set_compression(threshold);
+        }
+        if self.tracker.encryption_requested() {
+            return Err("the connection is encrypted from here on".into());
+        }
+        Ok(())
+    }
+}
+
+/// Prints packets as they are decoded and sums up what went wrong.
+pub struct Report {
+    /// Only print packets that did not decode cleanly.
+    pub problems_only: bool,
+    /// Adds the packet body as hex. Bodies of packets that could not be
+    /// decoded are always added.
+    pub raw: bool,
+    out: Box<dyn Write>,
+    connections: usize,
+    packets: usize,
+    clean: usize,
+    /// Counts by state, direction and id.
+    unknown: BTreeMap<String, usize>,
+    /// Counts and the most bytes left by state, direction and name.
+    unread: BTreeMap<String, (usize, usize)>,
+    /// Counts and the first error by state, direction and id.
+    errors: BTreeMap<String, (usize, String)>,
+    stopped: Vec<String>,
+}
+
+impl Report {
+    pub fn new(out: impl Write + 'static) -> Self {
+        Self {
+            problems_only: false,
+            raw: false,
+            out: Box::new(out),
+            connections: 0,
+            packets: 0,
+            clean: 0,
+            unknown: BTreeMap::new(),
+            unread: BTreeMap::new(),
+            errors: BTreeMap::new(),
+            stopped: Vec::new(),
+        }
+    }
+
+    /// Numbers a new connection.
+    pub fn next_connection(&mut self) -> usize {
+        self.connections += 1;
+        self.connections
+    }
+
+    /// Decodes and prints a packet.
+    pub fn packet(
+        &mut self,
+        connection: usize,
+        time: Duration,
+        bound: Bound,
+        state: ProtocolState,
+        raw: &RawPacket,
+    ) {
+        let decoded = decode(state, bound, raw);
+        let id = format!("0x{:02X}", raw.id.0);
+        let key = format!("{:<6} {:<11} {id}", state.name(), bound.name());
+        self.packets += 1;
+
+        let mut line = format!(
+            "{:>10.3}s #{connection} {} {:<9} {id} ",
+            time.as_secs_f64(),
+            bound.arrow(),
+            state.name(),
+        );
+        let problem = if decoded.unknown {
+            *self.unknown.entry(key).or_default() += 1;
+            line.push_str("unknown packet");
+            true
+        } else if let Some(error) = decoded.error {
+            let _ = write!(line, "error: {error}");
+            self.errors.entry(key).or_insert((0, error)).0 += 1;
+            true
+        } else {
+            line.push_str(decoded.detail.as_deref().unwrap_or_default());
+            if decoded.unread > 0 {
+                let _ = write!(line, " [{} unread bytes]", decoded.unread);
+                let key = format!(
+                    "{:<6} {:<11} {}",
+                    state.name(),
+                    bound.name(),
+                    decoded.name.as_deref().unwrap_or_default()
+                );
+                let (count, most) = self.unread.entry(key).or_default();
+                *count += 1;
+                *most = decoded.unread.max(*most);
+                true
+            } else {
+                self.clean += 1;
+                false
+            }
+        };
+        if self.problems_only && !problem {
+            return;
+        }
+        if self.raw || decoded.name.is_none() {
+            let _ = write!(line, " body={}", hex(&raw.body));
+        }
+        let _ = writeln!(self.out, "{line}");
+    }
+
+    /// Prints the summary.
+    pub fn finish(&mut self) {
+        let out = &mut self.out;
+        let _ = writeln!(
+            out,
+            "\n{} packets in {} connections, {} decoded cleanly",
+            self.packets, self.connections, self.clean
+        );
+        if !self.unknown.is_empty() {
+            let _ = writeln!(out, "\nUnknown packet ids:");
+            for (key, count) in &self.unknown {
+                let _ = writeln!(out, "{count:>8}  {key}");
+            }
+        }
+        if !self.unread.is_empty() {
+            let _ = writeln!(out, "\nPackets that left bytes unread:");
+            for (key, (count, most)) in &self.unread {
+                let _ = writeln!(out, "{count:>8}  {key} (up to {most} bytes)");
+            }
+        }
+        if !self.errors.is_empty() {
+            let _ = writeln!(out, "\nPackets that failed to decode:");
+            for (key, (count, error)) in &self.errors {
+                let _ = writeln!(out, "{count:>8}  {key}: {error}");
+            }
+        }
+        if !self.stopped.is_empty() {
+            let _ = writeln!(out, "\nConnections that could not be followed to the end:");
+            for reason in &self.stopped {
+                let _ = writeln!(out, "    {reason}");
+            }
+        }
+    }
+}
@@ -0,0 +1,99 @@
+use mooshroom::proto::{decode::Bound, recording::MAGIC};
+
+use crate::capture::is_capture;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Input {
+    /// A pcap or pcapng capture.
+    Capture,
+    /// A recording made by `mooshroom::proto::recording::Recorder`.
+    Recording,
+    HexDump,
+    /// The bytes one side of a connection sent.
+    Frames,
+}
+
+impl Input {
+    pub fn detect(bytes: &[u8]) -> Self {
+        if is_capture(bytes) {
+            Self::Capture
+        } else if bytes.starts_with(MAGIC) {
+            Self::Recording
+        } else if std::str::from_utf8(bytes)
+            .map(|s| !s.contains(|c: char| c.is_control() && !c.is_whitespace()))
+            .unwrap_or(false)
+        {
+            Self::HexDump
+        } else {
+            Self::Frames
+        }
+    }
+}
+
+/// Parses a hex dump into runs of bytes sent one way. Lines starting with
+/// `>` are serverbound and `<` clientbound, other lines continue the run
+/// before, which starts as `bound`. Offsets ending in `:` and anything after
+/// `#` are skipped.
+pub fn parse_hex_dump(text: &str, bound: Bound) -> Result<Vec<(Bound, Vec<u8>)>, String> {
+    let mut runs: Vec<(Bound, Vec<u8>)> = Vec::new();
+    let mut bound = bound;
+    for (number, line) in text.lines().enumerate() {
+        let mut line = line.split('#').next().unwrap_or_default().trim();
+        let marker = match line.chars().next() {
+            Some('>') => Some(Bound::Serverbound),
+            Some('<') => Some(Bound::Clientbound),
+            _ => None,
+        };
+        if let Some(marker) = marker {
+            line = &line[1..];
+            bound = marker;
+        }
+        if let Some((offset, rest)) = line.split_once(':') {
+            if offset.trim().chars().all(|c| c.is_ascii_hexdigit()) {
+                line = rest;
+            }
+        }
+        let digits: String = line.split_whitespace().collect();
+        if !digits.is_ascii() {
+            return Err(format!("line {}: invalid hex", number + 1));
+        }
+        if !digits.len().is_multiple_of(2) {
+            return Err(format!("line {}: odd number of hex digits", number + 1));
+        }
+        let bytes = (0..digits.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&digits[i..i + 2], 16))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| format!("line {}: invalid hex", number + 1))?;
+        match runs.last_mut() {
+            Some((b, run)) if *b == bound => run.extend(bytes),
+            _ if bytes.is_empty() => {}
+            _ => runs.push((bound, bytes)),
+        }
+    }
+    Ok(runs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_hex_dump() {
+        let dump = "\
+# a keep alive each way
+> 09 12 00000000
+  00000007
+< 09 11 0000 0000 0000 0007
+00000010: 0102  # more
+";
+        assert_eq!(Input::detect(dump.as_bytes()), Input::HexDump);
+        let runs = parse_hex_dump(dump, Bound::Serverbound).unwrap();
+        assert_eq!(runs.len(), 2);
+        assert_eq!(runs[0].0, Bound::Serverbound);
+        assert_eq!(runs[0].1, [9, 0x12, 0, 0, 0, 0, 0, 0, 0, 7]);
+        assert_eq!(runs[1].0, Bound::Clientbound);
+        assert_eq!(runs[1].1[10..], [1, 2]);
+        assert!(parse_hex_dump("> 123", Bound::Serverbound).is_err());
+    }
+}
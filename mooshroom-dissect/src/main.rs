@@ -0,0 +1,352 @@
+mod capture;
+mod dissect;
+mod input;
+mod reassembly;
+
+use std::{collections::HashMap, net::SocketAddr, process::exit, time::Duration};
+
+use dissect::{Dissector, Report};
+use input::{parse_hex_dump, Input};
+use mooshroom::proto::{
+    decode::Bound,
+    interceptor::Direction,
+    ping::DEFAULT_PORT,
+    recording::{ProtocolState, RecordingReader},
+};
+use reassembly::HalfStream;
+
+const USAGE: &str = "\
+Usage: mooshroom-dissect [options] <file>...
+
+Decodes every packet in pcap or pcapng captures, recordings, hex dumps or
+raw frame files, reporting unknown packet ids and bytes packets left unread.
+
+Hex dumps hold whitespace separated hex. Lines starting with `>` are
+serverbound and lines starting with `<` clientbound, other lines continue
+the line before. Raw frame files hold the bytes one side of a connection sent.
+
+Options:
+  -p, --port <port>       Server port to look for in captures [default: 25565]
+      --state <state>     State to start in when the start of a connection is
+                          missing: handshake, status, login or play [default: handshake]
+      --compression <n>   Compression threshold to start with
+      --hex               Read files as hex dumps instead of detecting their type
+      --frames            Read files as raw frame files
+      --clientbound       Hex dumps and frame files are clientbound unless marked
+      --problems          Only print packets that did not decode cleanly
+      --raw               Print packet bodies as hex
+  -h, --help              Print this help";
+
+struct Args {
+    /// Overrides detecting the type of each file.
+    input: Option<Input>,
+    port: u16,
+    state: ProtocolState,
+    compression: Option<i32>,
+    bound: Bound,
+    problems: bool,
+    raw: bool,
+    files: Vec<String>,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut parsed = Args {
+        input: None,
+        port: DEFAULT_PORT,
+        state: ProtocolState::Handshake,
+        compression: None,
+        bound: Bound::Serverbound,
+        problems: false,
+        raw: false,
+        files: Vec::new(),
+    };
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| args.next().ok_or(format!("{name} needs a value"));
+        match arg.as_str() {
+            "-p" | "--port" => {
+                parsed.port = value(&arg)?.parse().map_err(|_| "invalid port")?;
+            }
+            "--state" => parsed.state = parse_state(&value(&arg)?)?,
+            "--compression" => {
+                let threshold = value(&arg)?.parse().map_err(|_| "invalid threshold")?;
+                parsed.compression = Some(threshold);
+            }
+            "--hex" => parsed.input = Some(Input::HexDump),
+            "--frames" => parsed.input = Some(Input::Frames),
+            "--clientbound" => parsed.bound = Bound::Clientbound,
+            "--problems" => parsed.problems = true,
+            "--raw" => parsed.raw = true,
+            "-h" | "--help" => {
+                println!("{USAGE}");
+                exit(0);
+            }
+            a if a.starts_with('-') => return Err(format!("unknown option {a}")),
+            _ => parsed.files.push(arg),
+        }
+    }
+    if parsed.files.is_empty() {
+        return Err("missing files to dissect".into());
+    }
+    Ok(parsed)
+}
+
+fn parse_state(state: &str) -> Result<ProtocolState, String> {
+    match state {
+        "handshake" => Ok(ProtocolState::Handshake),
+        "status" => Ok(ProtocolState::Status),
+        "login" => Ok(ProtocolState::Login),
+        "play" => Ok(ProtocolState::Play),
+        _ => Err(format!("unknown state {state}")),
+    }
+}
+
+fn dissect(bytes: &[u8], args: &Args, report: &mut Report) -> Result<(), String> {
+    match args.input.unwrap_or_else(|| Input::detect(bytes)) {
+        Input::Capture => dissect_capture(bytes, args, report),
+        Input::Recording => {
+            let recording = RecordingReader::new(bytes).map_err(|e| e.to_string())?;
+            let connection = report.next_connection();
+            for frame in recording {
+                let frame = frame.map_err(|e| e.to_string())?;
+                let bound = match frame.direction {
+                    Direction::Outgoing => Bound::Serverbound,
+                    Direction::Incoming => Bound::Clientbound,
+                };
+                report.packet(connection, frame.time, bound, frame.state, &frame.packet);
+            }
+            Ok(())
+        }
+        Input::HexDump => {
+            let text = std::str::from_utf8(bytes).map_err(|e| e.to_string())?;
+            let mut dissector = Dissector::new(report, args.state, args.compression);
+            for (bound, run) in parse_hex_dump(text, args.bound)? {
+                dissector.feed(Duration::ZERO, bound, &run, report);
+            }
+            Ok(())
+        }
+        Input::Frames => {
+            Dissector::new(report, args.state, args.compression).feed(
+                Duration::ZERO,
+                args.bound,
+                bytes,
+                report,
+            );
+            Ok(())
+        }
+    }
+}
+
+struct Connection {
+    dissector: Dissector,
+    serverbound: HalfStream,
+    clientbound: HalfStream,
+}
+
+/// Reassembles the TCP connections to `args.port` and dissects them in the
+/// order their segments were captured.
+fn dissect_capture(bytes: &[u8], args: &Args, report: &mut Report) -> Result<(), String> {
+    let mut connections: HashMap<(SocketAddr, SocketAddr), Connection> = HashMap::new();
+    let mut start = None;
+    for frame in capture::read_capture(bytes)? {
+        let segment = match capture::tcp_segment(&frame) {
+            Some(s) => s,
+            None => continue,
+        };
+        // Keyed by client then server address.
+        let (bound, key) = if segment.dst.port() == args.port {
+            (Bound::Serverbound, (segment.src, segment.dst))
+        } else if segment.src.port() == args.port {
+            (Bound::Clientbound, (segment.dst, segment.src))
+        } else {
+            continue;
+        };
+        let opening = segment.syn && !segment.ack;
+        if opening {
+            // The ports have been reused for a new connection.
+            connections.remove(&key);
+        }
+        let connection = connections.entry(key).or_insert_with(|| {
+            let (state, compression) = if opening {
+                (ProtocolState::Handshake, None)
+            } else {
+                (args.state, args.compression)
+            };
+            Connection {
+                dissector: Dissector::new(report, state, compression),
+                serverbound: HalfStream::default(),
+                clientbound: HalfStream::default(),
+            }
+        });
+        let stream = match bound {
+            Bound::Serverbound => &mut connection.serverbound,
+            Bound::Clientbound => &mut connection.clientbound,
+        };
+        let ready = stream.push(segment.seq, segment.syn, segment.payload);
+        if !ready.is_empty() {
+            let time = segment
+                .time
+                .saturating_sub(*start.get_or_insert(segment.time));
+            connection.dissector.feed(time, bound, &ready, report);
+        }
+    }
+    Ok(())
+}
+
+fn main() {
+    let args = match parse_args() {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{e}\n\n{USAGE}");
+            exit(2);
+        }
+    };
+    let mut report = Report::new(std::io::stdout());
+    report.problems_only = args.problems;
+    report.raw = args.raw;
+
+    let mut failed = false;
+    for file in &args.files {
+        let result = std::fs::read(file)
+            .map_err(|e| e.to_string())
+            .and_then(|bytes| dissect(&bytes, &args, &mut report));
+        if let Err(e) = result {
+            eprintln!("{file}: {e}");
+            failed = true;
+        }
+    }
+    report.finish();
+    if failed {
+        exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use mooshroom::{
+        client::{
+            handshake::{Handshake, HandshakeState},
+            login::LoginStart,
+            metadata::KeepAliveResponse,
+        },
+        core::io::DEFAULT_PROTOCAL_VERSION,
+        proto::{
+            codec::{MooshroomCodec, RawPacket},
+            testing::SharedBuffer,
+        },
+        server::{
+            login::{LoginSuccess, SetCompression},
+            play::metadata::KeepAlive,
+        },
+    };
+
+    use super::*;
+
+    /// Builds an ethernet pcap of segments between a client and port 25565.
+    fn pcap(segments: &[(Bound, u32, bool, &[u8])]) -> Vec<u8> {
+        let mut pcap = vec![];
+        pcap.extend(0xa1b2_c3d4u32.to_le_bytes());
+        pcap.extend([
+            2, 0, 4, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0xff, 0xff, 0, 0, 1, 0, 0, 0,
+        ]);
+        for (i, (bound, seq, syn, payload)) in segments.iter().enumerate() {
+            let (src, dst, ports) = match bound {
+                Bound::Serverbound => ([10, 0, 0, 1], [10, 0, 0, 2], [50000u16, 25565]),
+                Bound::Clientbound => ([10, 0, 0, 2], [10, 0, 0, 1], [25565, 50000]),
+            };
+            let mut frame = vec![0; 12];
+            frame.extend([0x08, 0x00, 0x45, 0]);
+            frame.extend((40 + payload.len() as u16).to_be_bytes());
+            frame.extend([0, 0, 0x40, 0, 64, 6, 0, 0]);
+            frame.extend(src);
+            frame.extend(dst);
+            frame.extend(ports[0].to_be_bytes());
+            frame.extend(ports[1].to_be_bytes());
+            frame.extend(seq.to_be_bytes());
+            let flags = match (syn, bound) {
+                (true, Bound::Serverbound) => 0x02,
+                (true, Bound::Clientbound) => 0x12,
+                (false, _) => 0x10,
+            };
+            frame.extend([0, 0, 0, 0, 0x50, flags, 0xff, 0xff, 0, 0, 0, 0]);
+            frame.extend(*payload);
+
+            pcap.extend((i as u32).to_le_bytes());
+            pcap.extend(0u32.to_le_bytes());
+            pcap.extend((frame.len() as u32).to_le_bytes());
+            pcap.extend((frame.len() as u32).to_le_bytes());
+            pcap.extend(frame);
+        }
+        pcap
+    }
+
+    #[test]
+    fn test_dissect_capture() {
+        let mut client = MooshroomCodec::<DEFAULT_PROTOCAL_VERSION>::new();
+        let mut login = client
+            .encode(&Handshake {
+                protocol_version: 760.into(),
+                server_address: "localhost".into(),
+                server_port: 25565,
+                next_state: HandshakeState::Login,
+            })
+            .unwrap();
+        login.extend(client.encode(&LoginStart::default()).unwrap());
+
+        let mut server = MooshroomCodec::<DEFAULT_PROTOCAL_VERSION>::new();
+        let mut replies = server
+            .encode(&SetCompression {
+                threshold: 16.into(),
+            })
+            .unwrap();
+        server.set_compression(16);
+        replies.extend(server.encode(&LoginSuccess::default()).unwrap());
+        replies.extend(server.encode(&KeepAlive(7)).unwrap());
+        let mut long =
+            RawPacket::from_packet::<DEFAULT_PROTOCAL_VERSION, _>(&KeepAlive(8)).unwrap();
+        long.body.extend_from_slice(&[0; 20]);
+        replies.extend(server.encode_raw(&long).unwrap());
+        replies.extend(
+            server
+                .encode_raw(&RawPacket::new(0x7f.into(), &[1][..]))
+                .unwrap(),
+        );
+        client.set_compression(16);
+        let pong = client.encode(&KeepAliveResponse(7)).unwrap();
+
+        let (first, second) = login.split_at(5);
+        let capture = pcap(&[
+            (Bound::Serverbound, 999, true, &[]),
+            (Bound::Clientbound, 4999, true, &[]),
+            (Bound::Serverbound, 1005, false, second),
+            (Bound::Serverbound, 1000, false, first),
+            (Bound::Serverbound, 1000, false, first),
+            (Bound::Clientbound, 5000, false, &replies),
+            (Bound::Serverbound, 1000 + login.len() as u32, false, &pong),
+        ]);
+        assert_eq!(Input::detect(&capture), Input::Capture);
+
+        let args = Args {
+            input: None,
+            port: DEFAULT_PORT,
+            state: ProtocolState::Handshake,
+            compression: None,
+            bound: Bound::Serverbound,
+            problems: false,
+            raw: false,
+            files: Vec::new(),
+        };
+        let output = SharedBuffer::default();
+        let mut report = Report::new(output.clone());
+        dissect(&capture, &args, &mut report).unwrap();
+        report.finish();
+
+        let output = String::from_utf8(output.bytes()).unwrap();
+        assert!(output.contains("C->S handshake 0x00 Handshake {"));
+        assert!(output.contains("S->C login     0x02 LoginSuccess {"));
+        assert!(output.contains("S->C play      0x20 KeepAlive(8) [20 unread bytes]"));
+        assert!(output.contains("S->C play      0x7F unknown packet body=01"));
+        assert!(output.contains("C->S play      0x12 KeepAliveResponse(7)"));
+        assert!(output.contains("8 packets in 1 connections, 6 decoded cleanly"));
+    }
+}
@@ -0,0 +1,69 @@
+use std::collections::BTreeMap;
+
+/// Puts the payloads of one direction of a TCP connection back in order,
+/// dropping retransmissions.
+#[derive(Default)]
+pub struct HalfStream {
+    /// The sequence number of the first byte.
+    start: Option<u32>,
+    /// Offset of the next byte expected, from `start`.
+    next: u32,
+    /// Segments that arrived ahead of `next`, by offset.
+    pending: BTreeMap<u32, Vec<u8>>,
+}
+
+impl HalfStream {
+    /// Adds a segment, returning the bytes that are now in order.
+    pub fn push(&mut self, seq: u32, syn: bool, payload: &[u8]) -> Vec<u8> {
+        if syn {
+            // The SYN takes up a sequence number but carries no data.
+            self.start.get_or_insert(seq.wrapping_add(1));
+            return Vec::new();
+        }
+        let start = *self.start.get_or_insert(seq);
+        let offset = seq.wrapping_sub(start);
+        // Anything this far back is a retransmission from before the start.
+        if offset > u32::MAX / 2 || payload.is_empty() {
+            return Vec::new();
+        }
+        self.pending
+            .entry(offset)
+            .and_modify(|p| {
+                if p.len() < payload.len() {
+                    *p = payload.to_vec();
+                }
+            })
+            .or_insert_with(|| payload.to_vec());
+
+        let mut ready = Vec::new();
+        while let Some(entry) = self.pending.first_entry() {
+            let offset = *entry.key();
+            if offset > self.next {
+                break;
+            }
+            let payload = entry.remove();
+            let skip = (self.next - offset) as usize;
+            if skip < payload.len() {
+                ready.extend_from_slice(&payload[skip..]);
+                self.next += (payload.len() - skip) as u32;
+            }
+        }
+        ready
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reorder_and_retransmit() {
+        let mut stream = HalfStream::default();
+        assert!(stream.push(99, true, &[]).is_empty());
+        assert_eq!(stream.push(100, false, b"ab"), b"ab");
+        assert!(stream.push(104, false, b"ef").is_empty());
+        assert_eq!(stream.push(101, false, b"bcd"), b"cdef");
+        assert!(stream.push(100, false, b"abc").is_empty());
+        assert_eq!(stream.push(105, false, b"fgh"), b"gh");
+    }
+}
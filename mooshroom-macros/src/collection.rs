@@ -119,6 +119,17 @@ fn impl_collection_enum(ast: &syn::DeriveInput, data: &DataEnum) -> proc_macro2:
             }
         }
     });
+    let any_selectors = fields.iter().map(|(name, ty, attrs)| {
+        if let Some(FieldIdType::Range(_)) = &attrs.id {
+            quote! {
+                Self::#name(value) => <#ty as ::mooshroom_core::data::MooshroomCollection<PV>>::variant_any(value),
+            }
+        } else {
+            quote! {
+                Self::#name(value) => value,
+            }
+        }
+    });
     let n = quote! {
         #[automatically_derived]
         impl<const PV: ::mooshroom_core::io::Protocal> ::mooshroom_core::data::MooshroomCollection<PV> for #name {
@@ -139,6 +150,11 @@ fn impl_collection_enum(ast: &syn::DeriveInput, data: &DataEnum) -> proc_macro2:
                     #( #ids_selectors ) *
                 }
             }
+            fn variant_any(&self) -> &dyn ::std::any::Any {
+                match self {
+                    #( #any_selectors ) *
+                }
+            }
         }
     };
     //eprintln!("{:#}", n);
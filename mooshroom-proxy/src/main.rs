@@ -0,0 +1,117 @@
+mod packet_log;
+mod session;
+
+use std::{
+    net::{TcpListener, TcpStream},
+    process::exit,
+    sync::Arc,
+};
+
+use mooshroom::proto::ping::DEFAULT_PORT;
+use packet_log::{Format, PacketLog};
+
+const USAGE: &str = "\
+Usage: mooshroom-proxy [options] <upstream>
+
+Forwards connections to <upstream> (host[:port]), logging every packet.
+The upstream server must be in offline mode.
+
+Options:
+  -l, --listen <addr>     Address to listen on [default: 127.0.0.1:25566]
+      --json              Log one JSON object per packet
+      --include <names>   Only log these packets, comma separated names or ids like 0x21
+      --exclude <names>   Don't log these packets
+      --raw               Log packet bodies as hex
+  -h, --help              Print this help";
+
+struct Args {
+    listen: String,
+    upstream: String,
+    log: PacketLog,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut listen = "127.0.0.1:25566".to_string();
+    let mut upstream = None;
+    let mut log = PacketLog::new(Format::Text, std::io::stdout());
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| args.next().ok_or(format!("{name} needs a value"));
+        match arg.as_str() {
+            "-l" | "--listen" => listen = value(&arg)?,
+            "--json" => log.format = Format::Json,
+            "--include" => log.include.extend(split_list(&value(&arg)?)),
+            "--exclude" => log.exclude.extend(split_list(&value(&arg)?)),
+            "--raw" => log.raw = true,
+            "-h" | "--help" => {
+                println!("{USAGE}");
+                exit(0);
+            }
+            a if a.starts_with('-') => return Err(format!("unknown option {a}")),
+            _ if upstream.is_none() => upstream = Some(arg),
+            _ => return Err(format!("unexpected argument {arg}")),
+        }
+    }
+    let mut upstream = upstream.ok_or("missing upstream address")?;
+    if !upstream.contains(':') {
+        upstream = format!("{upstream}:{DEFAULT_PORT}");
+    }
+    Ok(Args {
+        listen,
+        upstream,
+        log,
+    })
+}
+
+fn split_list(list: &str) -> impl Iterator<Item = String> + '_ {
+    list.split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(String::from)
+}
+
+fn main() {
+    env_logger::init();
+    let args = match parse_args() {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{e}\n\n{USAGE}");
+            exit(2);
+        }
+    };
+    let listener = match TcpListener::bind(&args.listen) {
+        Ok(l) => l,
+        Err(e) => {
+            eprintln!("failed to listen on {}: {e}", args.listen);
+            exit(1);
+        }
+    };
+    eprintln!("proxying {} to {}", args.listen, args.upstream);
+
+    let log = Arc::new(args.log);
+    for (session, client) in listener.incoming().enumerate() {
+        let session = session + 1;
+        let client = match client {
+            Ok(c) => c,
+            Err(e) => {
+                eprintln!("failed to accept a connection: {e}");
+                continue;
+            }
+        };
+        let upstream_addr = args.upstream.clone();
+        let log = log.clone();
+        std::thread::spawn(move || {
+            let upstream = match TcpStream::connect(&upstream_addr) {
+                Ok(u) => u,
+                Err(e) => {
+                    eprintln!("#{session} failed to connect to {upstream_addr}: {e}");
+                    return;
+                }
+            };
+            match session::proxy(session, client, upstream, log) {
+                Ok(()) => eprintln!("#{session} closed"),
+                Err(e) => eprintln!("#{session} closed: {e}"),
+            }
+        });
+    }
+}
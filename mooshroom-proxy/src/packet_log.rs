@@ -0,0 +1,102 @@
+use std::{
+    fmt::Write as _,
+    io::Write,
+    sync::Mutex,
+    time::{Instant, SystemTime, UNIX_EPOCH},
+};
+
+use mooshroom::proto::{
+    codec::RawPacket,
+    decode::{decode, hex, Bound},
+    recording::ProtocolState,
+};
+use serde_json::json;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Text,
+    /// One JSON object per line.
+    Json,
+}
+
+/// Prints the packets passing through the proxy.
+pub struct PacketLog {
+    pub format: Format,
+    /// Packet names or ids like `0x21` to log. Empty logs everything.
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+    /// Adds the packet body as hex. Bodies of packets that could not be
+    /// decoded are always added.
+    pub raw: bool,
+    start: Instant,
+    out: Mutex<Box<dyn Write + Send>>,
+}
+
+impl PacketLog {
+    pub fn new(format: Format, out: impl Write + Send + 'static) -> Self {
+        Self {
+            format,
+            include: Vec::new(),
+            exclude: Vec::new(),
+            raw: false,
+            start: Instant::now(),
+            out: Mutex::new(Box::new(out)),
+        }
+    }
+
+    fn matches(list: &[String], name: Option<&str>, id: &str) -> bool {
+        list.iter()
+            .any(|f| Some(f.as_str()) == name || f.eq_ignore_ascii_case(id))
+    }
+
+    pub fn log(&self, session: usize, bound: Bound, state: ProtocolState, raw: &RawPacket) {
+        let decoded = decode(state, bound, raw);
+        let id = format!("0x{:02X}", raw.id.0);
+        let name = decoded.name.as_deref();
+        if (!self.include.is_empty() && !Self::matches(&self.include, name, &id))
+            || Self::matches(&self.exclude, name, &id)
+        {
+            return;
+        }
+        let body = (self.raw || name.is_none()).then(|| hex(&raw.body));
+
+        let line = match self.format {
+            Format::Text => {
+                let mut line = format!(
+                    "{:>10.3}s #{session} {} {:<9} {id} {}",
+                    self.start.elapsed().as_secs_f64(),
+                    bound.arrow(),
+                    state.name(),
+                    decoded.detail.as_deref().unwrap_or("?"),
+                );
+                if decoded.unread > 0 {
+                    let _ = write!(line, " [{} unread bytes]", decoded.unread);
+                }
+                if let Some(body) = body {
+                    let _ = write!(line, " body={body}");
+                }
+                line
+            }
+            Format::Json => {
+                let time = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_millis() as u64;
+                json!({
+                    "time": time,
+                    "session": session,
+                    "direction": bound.name(),
+                    "state": state.name(),
+                    "id": raw.id.0,
+                    "name": name,
+                    "length": raw.body.len(),
+                    "packet": decoded.detail,
+                    "unread": decoded.unread,
+                    "body": body,
+                })
+                .to_string()
+            }
+        };
+        let _ = writeln!(self.out.lock().unwrap(), "{line}");
+    }
+}
@@ -0,0 +1,181 @@
+use std::{
+    io::{Read, Write},
+    net::{Shutdown, TcpStream},
+    sync::{Arc, Mutex},
+};
+
+use mooshroom::{
+    core::{
+        error::{MooshroomError, Result},
+        io::DEFAULT_PROTOCAL_VERSION,
+    },
+    proto::{
+        codec::{MooshroomCodec, RawPacket},
+        decode::{Bound, StateTracker},
+    },
+};
+
+use crate::packet_log::PacketLog;
+
+const PV: usize = DEFAULT_PROTOCAL_VERSION;
+
+/// One direction of a proxied connection. Each leg decodes with the codec of
+/// the socket it reads from and encodes with the codec of the one it writes
+/// to, so compression is tracked per socket.
+struct Leg {
+    session: usize,
+    bound: Bound,
+    src: TcpStream,
+    dst: TcpStream,
+    rx: MooshroomCodec<PV>,
+    tx: MooshroomCodec<PV>,
+    /// Shared by both legs, since each sees half of what moves it along.
+    tracker: Arc<Mutex<StateTracker>>,
+    log: Arc<PacketLog>,
+}
+
+impl Leg {
+    /// Forwards packets until either side closes, then closes both.
+    fn pump(mut self) -> Result<()> {
+        let mut buffer = [0; 4096];
+        let result = loop {
+            match self.src.read(&mut buffer) {
+                Ok(0) => break Ok(()),
+                Ok(n) => self.rx.add_bytes(&buffer[..n]),
+                Err(e) => break Err(e.into()),
+            }
+            if let Err(e) = self.forward_buffered() {
+                break Err(e);
+            }
+        };
+        let _ = self.src.shutdown(Shutdown::Both);
+        let _ = self.dst.shutdown(Shutdown::Both);
+        result
+    }
+
+    fn forward_buffered(&mut self) -> Result<()> {
+        while let Some(raw) = self.rx.read_raw()? {
+            let compression = self.track(&raw)?;
+            // Everything after SetCompression is compressed, on both sockets.
+            if let Some(threshold) = compression {
+                self.rx.set_compression(threshold);
+            }
+            self.dst.write_all(&self.tx.encode_raw(&raw)?)?;
+            if let Some(threshold) = compression {
+                self.tx.set_compression(threshold);
+            }
+        }
+        Ok(())
+    }
+
+    /// Logs the packet and follows the connection state, returning the new
+    /// threshold if the packet enables compression.
+    fn track(&self, raw: &RawPacket) -> Result<Option<i32>> {
+        let mut tracker = self.tracker.lock().unwrap();
+        self.log.log(self.session, self.bound, tracker.state(), raw);
+        let compression = tracker.observe(self.bound, raw)?;
+        if tracker.encryption_requested() {
+            return Err(MooshroomError::AuthenticationFailed(
+                "the upstream server is in online mode, which can't be proxied".into(),
+            ));
+        }
+        Ok(compression)
+    }
+}
+
+/// Proxies `client` to `upstream` until either disconnects.
+pub fn proxy(
+    session: usize,
+    client: TcpStream,
+    upstream: TcpStream,
+    log: Arc<PacketLog>,
+) -> Result<()> {
+    let tracker = Arc::new(Mutex::new(StateTracker::default()));
+    let (client_rx, client_tx) = MooshroomCodec::new().split();
+    let (upstream_rx, upstream_tx) = MooshroomCodec::new().split();
+    let serverbound = Leg {
+        session,
+        bound: Bound::Serverbound,
+        src: client.try_clone()?,
+        dst: upstream.try_clone()?,
+        rx: client_rx,
+        tx: upstream_tx,
+        tracker: tracker.clone(),
+        log: log.clone(),
+    };
+    let clientbound = Leg {
+        session,
+        bound: Bound::Clientbound,
+        src: upstream,
+        dst: client,
+        rx: upstream_rx,
+        tx: client_tx,
+        tracker,
+        log,
+    };
+
+    let serverbound = std::thread::spawn(move || {
+        if let Err(e) = serverbound.pump() {
+            log::debug!("#{session} client connection ended: {e}");
+        }
+    });
+    let result = clientbound.pump();
+    let _ = serverbound.join();
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::TcpListener;
+
+    use mooshroom::{
+        client::login::LoginStart,
+        proto::{
+            state::Connection,
+            testing::{spawn_limbo_server, SharedBuffer},
+        },
+        server::play::PlayStage,
+    };
+
+    use super::*;
+    use crate::packet_log::Format;
+
+    #[test]
+    fn test_proxy_with_compression() {
+        let upstream_addr = spawn_limbo_server();
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let proxy_addr = listener.local_addr().unwrap();
+        let output = SharedBuffer::default();
+        let mut log = PacketLog::new(Format::Text, output.clone());
+        log.exclude = vec!["0x21".into()];
+        std::thread::spawn(move || {
+            let (client, _) = listener.accept().unwrap();
+            let upstream = TcpStream::connect(upstream_addr).unwrap();
+            let _ = proxy(1, client, upstream, Arc::new(log));
+        });
+
+        let start = LoginStart {
+            name: "Steve".into(),
+            ..Default::default()
+        };
+        let mut conn = Connection::connect(proxy_addr)
+            .unwrap()
+            .login("localhost", proxy_addr.port())
+            .unwrap()
+            .login_offline(&start)
+            .unwrap();
+        assert_eq!(conn.proto().codec.compression(), Some(64));
+        while !matches!(
+            conn.read().unwrap(),
+            PlayStage::SynchronizePlayerPosition(_)
+        ) {}
+        drop(conn);
+
+        let output = String::from_utf8(output.bytes()).unwrap();
+        assert!(output.contains("C->S handshake 0x00 Handshake {"));
+        assert!(output.contains("S->C login     0x03 SetCompression {"));
+        assert!(output.contains("S->C play      0x25 LoginPlay {"));
+        assert!(!output.contains("ChunkData"));
+    }
+}
@@ -1,6 +1,15 @@
 use std::net::TcpStream;
 
-use mooshroom::{proto::connection::MooshroomConnection, server::play::PlayStage};
+use mooshroom::{
+    proto::{
+        connection::{Housekeeping, MooshroomConnection},
+        events::MooshroomClient,
+    },
+    server::play::{
+        player::{CombatDeath, Respawn, SetHealth},
+        world::PlayerChatMessage,
+    },
+};
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     env_logger::init();
@@ -13,35 +22,33 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     println!("Connected! handshaking...");
 
+    c.set_housekeeping(Housekeeping::enabled());
     c.handshake_offline()?;
 
     println!("reading play packets...");
 
-    loop {
-        let packet = c.next_play_packet()?;
-
-        match packet {
-            PlayStage::KeepAlive(id) => {
-                println!("Sent keepalive");
-                c.respond_to_keep_alive(id.0)?;
-            }
-            PlayStage::SetHealth(p) => {
-                println!("{:#?}", p);
-                if p.health <= 0. {
-                    println!("respawning...");
-                    c.respawn()?;
-                }
-            }
-            PlayStage::PlayerChatMessage(c) => {
-                println!("{}", c.plain_message)
-            }
-            PlayStage::Respawn(p) => {
-                println!("{:#?}", p);
-            }
-            PlayStage::CombatDeath(p) => {
-                println!("{:#?}", p);
+    let mut client = MooshroomClient::new(c, ());
+    client
+        .on::<SetHealth>(|ctx, p| {
+            println!("{:#?}", p);
+            if p.health <= 0. {
+                println!("respawning...");
+                ctx.connection().respawn()?;
             }
-            _ => {}
-        }
-    }
+            Ok(())
+        })
+        .on::<PlayerChatMessage>(|_, c| {
+            println!("{}", c.plain_message);
+            Ok(())
+        })
+        .on::<Respawn>(|_, p| {
+            println!("{:#?}", p);
+            Ok(())
+        })
+        .on::<CombatDeath>(|_, p| {
+            println!("{:#?}", p);
+            Ok(())
+        });
+    client.run()?;
+    Ok(())
 }
@@ -1,32 +1,21 @@
-use std::net::TcpStream;
-
-use mooshroom::{
-    client::{
-        handshake::{Handshake, HandshakeState},
-        status::StatusRequest,
-    },
-    proto::MooshroomProto,
-};
+use mooshroom::proto::ping::ping_server;
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    println!("Connecting to 127.0.0.1:25565");
-
-    let mut m = {
-        let conn = TcpStream::connect("127.0.0.1:25565")?;
-        MooshroomProto::new(conn)
-    };
-
-    println!("Connected!");
+    println!("Pinging 127.0.0.1:25565");
 
-    m.write_packet(&Handshake {
-        server_address: "127.0.0.1".into(),
-        server_port: 25565,
-        protocol_version: (-1).into(),
-        next_state: HandshakeState::Status,
-    })?;
+    let ping = ping_server("127.0.0.1:25565")?;
+    let status = &ping.status;
 
-    let status = m.send_command(&StatusRequest)?;
-    println!("{:?}", status);
+    println!("{} ({})", status.version.name, status.version.protocol);
+    println!("{}", status.description.to_plain());
+    println!("{}/{} players", status.players.online, status.players.max);
+    for player in &status.players.sample {
+        println!("  {}", player.name);
+    }
+    if let Some(png) = status.favicon_png()? {
+        println!("favicon: {} bytes", png.len());
+    }
+    println!("latency: {:?}", ping.latency);
 
     Ok(())
 }
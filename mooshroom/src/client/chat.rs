@@ -0,0 +1,51 @@
+use mooshroom_macros::Mooshroom;
+
+use crate::shared::PreviousMessage;
+
+#[derive(Debug, Clone, Default, PartialEq, Mooshroom)]
+#[packet_id(0x03)]
+pub struct MessageAcknowledgment {
+    pub last_seen_messages: Vec<PreviousMessage>,
+    pub last_received: Option<PreviousMessage>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Mooshroom)]
+pub struct ArgumentSignature {
+    pub argument_name: String,
+    pub signature: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Mooshroom)]
+#[packet_id(0x04)]
+pub struct ChatCommand {
+    /// Without the leading slash.
+    pub command: String,
+    /// Milliseconds since the unix epoch.
+    pub timestamp: i64,
+    pub salt: i64,
+    pub argument_signatures: Vec<ArgumentSignature>,
+    pub signed_preview: bool,
+    pub last_seen_messages: Vec<PreviousMessage>,
+    pub last_received: Option<PreviousMessage>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Mooshroom)]
+#[packet_id(0x05)]
+pub struct ChatMessage {
+    pub message: String,
+    /// Milliseconds since the unix epoch.
+    pub timestamp: i64,
+    pub salt: i64,
+    /// Empty when the message is unsigned.
+    pub signature: Vec<u8>,
+    pub signed_preview: bool,
+    pub last_seen_messages: Vec<PreviousMessage>,
+    pub last_received: Option<PreviousMessage>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Mooshroom)]
+#[packet_id(0x06)]
+pub struct ChatPreview {
+    pub query: i32,
+    pub message: String,
+}
@@ -0,0 +1,73 @@
+use mooshroom_core::varint::VarInt;
+use mooshroom_macros::Mooshroom;
+
+use crate::server::play::crafting::Slot;
+
+/// The slot number for clicks outside the window.
+pub const OUTSIDE_WINDOW: i16 = -999;
+
+#[derive(Debug, Clone, Default, PartialEq, Mooshroom)]
+#[packet_id(0x0A)]
+pub struct ClickContainerButton {
+    pub window_id: i8,
+    pub button_id: i8,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Mooshroom)]
+#[repr(i32)]
+#[value_type(VarInt)]
+pub enum ClickMode {
+    #[default]
+    Pickup = 0,
+    QuickMove = 1,
+    Swap = 2,
+    Clone = 3,
+    Throw = 4,
+    QuickCraft = 5,
+    PickupAll = 6,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Mooshroom)]
+pub struct ChangedSlot {
+    pub slot: i16,
+    pub item: Slot,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Mooshroom)]
+#[packet_id(0x0B)]
+pub struct ClickContainer {
+    pub window_id: u8,
+    /// The last state id received for the window.
+    pub state_id: VarInt,
+    pub slot: i16,
+    pub button: i8,
+    pub mode: ClickMode,
+    /// The slots the client expects the click to change, with their new
+    /// contents.
+    pub changed_slots: Vec<ChangedSlot>,
+    pub carried_item: Slot,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Mooshroom)]
+#[packet_id(0x0C)]
+pub struct CloseContainer(pub u8);
+
+#[derive(Debug, Clone, Default, PartialEq, Mooshroom)]
+#[packet_id(0x1A)]
+pub struct PickItem {
+    pub slot: VarInt,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Mooshroom)]
+#[packet_id(0x28)]
+pub struct SetHeldItem {
+    /// Hotbar slot, from 0 to 8.
+    pub slot: i16,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Mooshroom)]
+#[packet_id(0x2B)]
+pub struct SetCreativeModeSlot {
+    pub slot: i16,
+    pub clicked_item: Slot,
+}
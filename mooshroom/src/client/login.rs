@@ -1,4 +1,9 @@
-use mooshroom_core::{error::Result, io::MooshroomReadProto};
+use mooshroom_core::{
+    error::Result,
+    io::{MooshroomReadProto, MooshroomReadable, MooshroomWritable, MooshroomWriteProto},
+    primitives::RemainingBytes,
+    varint::VarInt,
+};
 use mooshroom_macros::Mooshroom;
 
 use crate::shared::SignatureData;
@@ -16,38 +21,56 @@ pub struct SignatureInfo {
     pub salt: i64,
     pub signature: Vec<u8>,
 }
+/// How the client proves it decrypted the verify token. 1.19 clients with a
+/// chat signing key sign a salted hash of the token instead of returning it.
+#[derive(Debug, Clone)]
+pub enum EncryptionVerification {
+    VerifyToken(Vec<u8>),
+    Signature(SignatureInfo),
+}
+
+impl Default for EncryptionVerification {
+    fn default() -> Self {
+        Self::VerifyToken(Vec::new())
+    }
+}
+
+impl<const PV: usize> MooshroomReadable<PV> for EncryptionVerification {
+    fn read(reader: &mut impl std::io::Read) -> Result<Self> {
+        if bool::read_proto::<PV>(reader)? {
+            Ok(Self::VerifyToken(Vec::read_proto::<PV>(reader)?))
+        } else {
+            Ok(Self::Signature(SignatureInfo::read_proto::<PV>(reader)?))
+        }
+    }
+}
+
+impl<const PV: usize> MooshroomWritable<PV> for EncryptionVerification {
+    fn write(&self, writer: &mut impl std::io::Write) -> Result<()> {
+        match self {
+            Self::VerifyToken(token) => {
+                true.write_proto::<PV>(writer)?;
+                token.write_proto::<PV>(writer)
+            }
+            Self::Signature(signature) => {
+                false.write_proto::<PV>(writer)?;
+                signature.write_proto::<PV>(writer)
+            }
+        }
+    }
+}
+
 #[derive(Debug, Clone, Default, Mooshroom)]
 #[packet_id(0x01)]
 pub struct EncryptionResponse {
     pub shared_secret: Vec<u8>,
-    pub has_verify_token: bool,
-    #[read(read_verify_token, has_verify_token)]
-    pub verify_token: Option<Vec<u8>>,
-    #[read(read_signature, has_verify_token)]
-    pub signature: Option<SignatureInfo>,
-}
-
-fn read_verify_token<const PV: usize>(
-    reader: &mut impl std::io::Read,
-    has_verify_token: &bool,
-) -> Result<Option<Vec<u8>>> {
-    if *has_verify_token {
-        Ok(Some(Vec::read_proto::<PV>(reader)?))
-    } else {
-        Ok(None)
-    }
+    pub verification: EncryptionVerification,
 }
-fn read_signature<const PV: usize>(
-    reader: &mut impl std::io::Read,
-    has_verify_token: &bool,
-) -> Result<Option<SignatureInfo>> {
-    let r = if *has_verify_token {
-        None
-    } else {
-        Some(SignatureInfo {
-            salt: i64::read_proto::<PV>(reader)?,
-            signature: Vec::read_proto::<PV>(reader)?,
-        })
-    };
-    Ok(r)
+
+/// `data` is `None` when the client does not understand the channel.
+#[derive(Debug, Clone, Default, Mooshroom)]
+#[packet_id(0x02)]
+pub struct LoginPluginResponse {
+    pub message_id: VarInt,
+    pub data: Option<RemainingBytes>,
 }
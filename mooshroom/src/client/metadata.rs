@@ -1,5 +1,119 @@
-use mooshroom_macros::Mooshroom;
+use mooshroom_core::{
+    primitives::{Identifier, RemainingBytes},
+    varint::VarInt,
+};
+use mooshroom_macros::{Mooshroom, MooshroomBitfield};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Mooshroom)]
+#[repr(i32)]
+#[value_type(VarInt)]
+pub enum ChatMode {
+    #[default]
+    Enabled = 0,
+    CommandsOnly = 1,
+    Hidden = 2,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, MooshroomBitfield)]
+#[value_type(u8)]
+pub struct SkinParts {
+    #[mask(0x01)]
+    pub cape: bool,
+    #[mask(0x02)]
+    pub jacket: bool,
+    #[mask(0x04)]
+    pub left_sleeve: bool,
+    #[mask(0x08)]
+    pub right_sleeve: bool,
+    #[mask(0x10)]
+    pub left_pants_leg: bool,
+    #[mask(0x20)]
+    pub right_pants_leg: bool,
+    #[mask(0x40)]
+    pub hat: bool,
+}
+
+impl Default for SkinParts {
+    fn default() -> Self {
+        Self {
+            cape: true,
+            jacket: true,
+            left_sleeve: true,
+            right_sleeve: true,
+            left_pants_leg: true,
+            right_pants_leg: true,
+            hat: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Mooshroom)]
+#[repr(i32)]
+#[value_type(VarInt)]
+pub enum MainHand {
+    Left = 0,
+    #[default]
+    Right = 1,
+}
+
+/// The client's settings. Defaults match a fresh vanilla install.
+#[derive(Debug, Clone, PartialEq, Eq, Mooshroom)]
+#[packet_id(0x08)]
+pub struct ClientInformation {
+    pub locale: String,
+    pub view_distance: i8,
+    pub chat_mode: ChatMode,
+    pub chat_colors: bool,
+    pub displayed_skin_parts: SkinParts,
+    pub main_hand: MainHand,
+    pub enable_text_filtering: bool,
+    /// Whether to show up in the server list's player sample.
+    pub allow_server_listings: bool,
+}
+
+impl Default for ClientInformation {
+    fn default() -> Self {
+        Self {
+            locale: "en_us".into(),
+            view_distance: 12,
+            chat_mode: ChatMode::Enabled,
+            chat_colors: true,
+            displayed_skin_parts: SkinParts::default(),
+            main_hand: MainHand::Right,
+            enable_text_filtering: false,
+            allow_server_listings: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Mooshroom)]
+#[packet_id(0x0D)]
+pub struct PluginMessage {
+    pub channel: Identifier,
+    pub data: RemainingBytes,
+}
 
 #[derive(Debug, Clone, Default, Mooshroom)]
 #[packet_id(0x12)]
 pub struct KeepAliveResponse(pub i64);
+
+#[derive(Debug, Clone, Default, Mooshroom)]
+#[packet_id(0x20)]
+pub struct Pong(pub i32);
+
+#[derive(Debug, Clone, Default, Copy, PartialEq, Eq, Mooshroom)]
+#[repr(i32)]
+#[value_type(VarInt)]
+pub enum ResourcePackResult {
+    #[default]
+    SuccessfullyLoaded = 0,
+    Declined = 1,
+    FailedDownload = 2,
+    Accepted = 3,
+}
+
+#[derive(Debug, Clone, Default, Mooshroom)]
+#[packet_id(0x24)]
+pub struct ResourcePackResponse {
+    pub result: ResourcePackResult,
+}
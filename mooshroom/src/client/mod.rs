@@ -1,5 +1,58 @@
+pub mod chat;
 pub mod handshake;
+pub mod inventory;
 pub mod login;
 pub mod metadata;
+pub mod movement;
 pub mod player;
 pub mod status;
+pub mod world;
+use mooshroom_macros::MooshroomCollection;
+
+/// What the client sends in each state, for reading on the server side.
+#[derive(Debug, Clone, MooshroomCollection)]
+pub enum StatusStage {
+    Request(status::StatusRequest),
+    Ping(status::PingRequest),
+}
+
+#[derive(Debug, Clone, MooshroomCollection)]
+pub enum LoginStage {
+    Start(login::LoginStart),
+    EncryptionResponse(login::EncryptionResponse),
+    LoginPluginResponse(login::LoginPluginResponse),
+}
+
+#[derive(Debug, Clone, MooshroomCollection)]
+pub enum PlayStage {
+    ConfirmTeleportation(player::ConfirmTeleportation),
+    MessageAcknowledgment(chat::MessageAcknowledgment),
+    ChatCommand(chat::ChatCommand),
+    ChatMessage(chat::ChatMessage),
+    ChatPreview(chat::ChatPreview),
+    Action(player::Action),
+    ClientInformation(metadata::ClientInformation),
+    ClickContainerButton(inventory::ClickContainerButton),
+    ClickContainer(inventory::ClickContainer),
+    CloseContainer(inventory::CloseContainer),
+    PluginMessage(metadata::PluginMessage),
+    Interact(world::Interact),
+    KeepAlive(metadata::KeepAliveResponse),
+    SetPlayerPosition(movement::SetPlayerPosition),
+    SetPlayerPositionAndRotation(movement::SetPlayerPositionAndRotation),
+    SetPlayerRotation(movement::SetPlayerRotation),
+    SetPlayerOnGround(movement::SetPlayerOnGround),
+    MoveVehicle(movement::MoveVehicle),
+    PaddleBoat(movement::PaddleBoat),
+    PickItem(inventory::PickItem),
+    PlayerAction(world::PlayerAction),
+    PlayerCommand(movement::PlayerCommand),
+    Pong(metadata::Pong),
+    ResourcePack(metadata::ResourcePackResponse),
+    SetHeldItem(inventory::SetHeldItem),
+    SetCreativeModeSlot(inventory::SetCreativeModeSlot),
+    UpdateSign(world::UpdateSign),
+    SwingArm(world::SwingArm),
+    UseItemOn(world::UseItemOn),
+    UseItem(world::UseItem),
+}
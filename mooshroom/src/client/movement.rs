@@ -0,0 +1,79 @@
+use mooshroom_core::varint::VarInt;
+use mooshroom_macros::Mooshroom;
+
+#[derive(Debug, Clone, Default, PartialEq, Mooshroom)]
+#[packet_id(0x14)]
+pub struct SetPlayerPosition {
+    pub x: f64,
+    /// Feet position.
+    pub y: f64,
+    pub z: f64,
+    pub on_ground: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Mooshroom)]
+#[packet_id(0x15)]
+pub struct SetPlayerPositionAndRotation {
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+    pub yaw: f32,
+    pub pitch: f32,
+    pub on_ground: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Mooshroom)]
+#[packet_id(0x16)]
+pub struct SetPlayerRotation {
+    pub yaw: f32,
+    pub pitch: f32,
+    pub on_ground: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Mooshroom)]
+#[packet_id(0x17)]
+pub struct SetPlayerOnGround {
+    pub on_ground: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Mooshroom)]
+#[packet_id(0x18)]
+pub struct MoveVehicle {
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+    pub yaw: f32,
+    pub pitch: f32,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Mooshroom)]
+#[packet_id(0x19)]
+pub struct PaddleBoat {
+    pub left_paddle_turning: bool,
+    pub right_paddle_turning: bool,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Mooshroom)]
+#[repr(i32)]
+#[value_type(VarInt)]
+pub enum PlayerCommandAction {
+    #[default]
+    StartSneaking = 0,
+    StopSneaking = 1,
+    LeaveBed = 2,
+    StartSprinting = 3,
+    StopSprinting = 4,
+    StartJumpWithHorse = 5,
+    StopJumpWithHorse = 6,
+    OpenHorseInventory = 7,
+    StartFlyingWithElytra = 8,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Mooshroom)]
+#[packet_id(0x1E)]
+pub struct PlayerCommand {
+    pub entity_id: VarInt,
+    pub action: PlayerCommandAction,
+    /// Only used by `StartJumpWithHorse`, from 0 to 100.
+    pub jump_boost: VarInt,
+}
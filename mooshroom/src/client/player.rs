@@ -9,6 +9,14 @@ use mooshroom_core::{
     },
     varint::VarInt,
 };
+use mooshroom_macros::Mooshroom;
+
+#[derive(Debug, Clone, Default, Mooshroom)]
+#[packet_id(0x00)]
+pub struct ConfirmTeleportation {
+    pub teleport_id: VarInt,
+}
+
 #[derive(Debug, Copy, Clone, Default)]
 pub enum Action {
     #[default]
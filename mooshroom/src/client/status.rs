@@ -10,7 +10,7 @@ pub struct StatusRequest;
 #[derive(Debug, Clone, Default, Mooshroom)]
 #[packet_id(0x01)]
 #[response(PingResponse)]
-pub struct PingRequest(u64);
+pub struct PingRequest(pub u64);
 
 #[cfg(test)]
 mod tests {
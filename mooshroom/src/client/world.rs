@@ -0,0 +1,164 @@
+use mooshroom_core::{
+    error::Result,
+    io::{MooshroomReadProto, MooshroomReadable, MooshroomWritable, MooshroomWriteProto},
+    primitives::{Position, Vec3},
+    varint::VarInt,
+};
+use mooshroom_macros::Mooshroom;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Mooshroom)]
+#[repr(i32)]
+#[value_type(VarInt)]
+pub enum Hand {
+    #[default]
+    MainHand = 0,
+    OffHand = 1,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Mooshroom)]
+#[repr(u8)]
+pub enum BlockFace {
+    #[default]
+    Bottom = 0,
+    Top = 1,
+    North = 2,
+    South = 3,
+    West = 4,
+    East = 5,
+}
+
+impl BlockFace {
+    /// The middle of the face, as a cursor position for [`UseItemOn`].
+    pub fn center(self) -> Vec3<f32> {
+        let (x, y, z) = match self {
+            Self::Bottom => (0.5, 0., 0.5),
+            Self::Top => (0.5, 1., 0.5),
+            Self::North => (0.5, 0.5, 0.),
+            Self::South => (0.5, 0.5, 1.),
+            Self::West => (0., 0.5, 0.5),
+            Self::East => (1., 0.5, 0.5),
+        };
+        Vec3 { x, y, z }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Mooshroom)]
+#[repr(i32)]
+#[value_type(VarInt)]
+pub enum InteractKind {
+    #[default]
+    Interact = 0,
+    Attack = 1,
+    InteractAt = 2,
+}
+
+/// What an [`Interact`] does. Only interactions carry a hand, and only
+/// `InteractAt` a target position relative to the entity.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum InteractAction {
+    Interact(Hand),
+    #[default]
+    Attack,
+    InteractAt {
+        target: Vec3<f32>,
+        hand: Hand,
+    },
+}
+
+impl<const PV: usize> MooshroomReadable<PV> for InteractAction {
+    fn read(reader: &mut impl std::io::Read) -> Result<Self> {
+        Ok(match InteractKind::read_proto::<PV>(reader)? {
+            InteractKind::Interact => Self::Interact(Hand::read_proto::<PV>(reader)?),
+            InteractKind::Attack => Self::Attack,
+            InteractKind::InteractAt => Self::InteractAt {
+                target: Vec3::read_proto::<PV>(reader)?,
+                hand: Hand::read_proto::<PV>(reader)?,
+            },
+        })
+    }
+}
+
+impl<const PV: usize> MooshroomWritable<PV> for InteractAction {
+    fn write(&self, writer: &mut impl std::io::Write) -> Result<()> {
+        match self {
+            Self::Interact(hand) => {
+                InteractKind::Interact.write_proto::<PV>(writer)?;
+                hand.write_proto::<PV>(writer)
+            }
+            Self::Attack => InteractKind::Attack.write_proto::<PV>(writer),
+            Self::InteractAt { target, hand } => {
+                InteractKind::InteractAt.write_proto::<PV>(writer)?;
+                target.write_proto::<PV>(writer)?;
+                hand.write_proto::<PV>(writer)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Mooshroom)]
+#[packet_id(0x10)]
+pub struct Interact {
+    pub entity_id: VarInt,
+    pub action: InteractAction,
+    pub sneaking: bool,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Mooshroom)]
+#[repr(i32)]
+#[value_type(VarInt)]
+pub enum PlayerActionStatus {
+    #[default]
+    StartedDigging = 0,
+    CancelledDigging = 1,
+    FinishedDigging = 2,
+    DropItemStack = 3,
+    DropItem = 4,
+    /// Also used to finish eating.
+    ShootArrow = 5,
+    SwapItemInHand = 6,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Mooshroom)]
+#[packet_id(0x1D)]
+pub struct PlayerAction {
+    pub status: PlayerActionStatus,
+    pub location: Position,
+    pub face: BlockFace,
+    pub sequence: VarInt,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Mooshroom)]
+#[packet_id(0x2E)]
+pub struct UpdateSign {
+    pub location: Position,
+    pub line_1: String,
+    pub line_2: String,
+    pub line_3: String,
+    pub line_4: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Mooshroom)]
+#[packet_id(0x2F)]
+pub struct SwingArm {
+    pub hand: Hand,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Mooshroom)]
+#[packet_id(0x31)]
+pub struct UseItemOn {
+    pub hand: Hand,
+    pub location: Position,
+    /// A VarInt on the wire, which is the same single byte for every face.
+    pub face: BlockFace,
+    /// Where on the face the block was clicked, from 0 to 1.
+    pub cursor: Vec3<f32>,
+    pub inside_block: bool,
+    pub sequence: VarInt,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Mooshroom)]
+#[packet_id(0x32)]
+pub struct UseItem {
+    pub hand: Hand,
+    pub sequence: VarInt,
+}
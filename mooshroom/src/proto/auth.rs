@@ -0,0 +1,254 @@
+use std::{
+    collections::HashMap,
+    io::{Read, Write},
+    sync::{Arc, Mutex},
+};
+
+use num_bigint::BigInt;
+use rand::Rng;
+use rsa::{
+    pkcs8::{DecodePublicKey, EncodePublicKey},
+    Pkcs1v15Encrypt,
+    RsaPrivateKey,
+    RsaPublicKey,
+};
+use sha1::{Digest, Sha1};
+
+use super::MooshroomProto;
+use crate::{
+    client::login::{EncryptionResponse, EncryptionVerification},
+    core::error::{MooshroomError, Result},
+    server::login::{EncryptionRequest, LoginSuccess, Property},
+};
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GameProfile {
+    pub uuid: uuid::Uuid,
+    pub name: String,
+    pub properties: Vec<Property>,
+}
+
+impl From<LoginSuccess> for GameProfile {
+    fn from(p: LoginSuccess) -> Self {
+        Self {
+            uuid: p.uuid,
+            name: p.username,
+            properties: p.properties,
+        }
+    }
+}
+
+impl From<GameProfile> for LoginSuccess {
+    fn from(p: GameProfile) -> Self {
+        Self {
+            uuid: p.uuid,
+            username: p.name,
+            properties: p.properties,
+        }
+    }
+}
+
+/// The session service both sides consult during an online-mode login, like
+/// Mojang's `join` and `hasJoined` endpoints.
+pub trait SessionAuthenticator {
+    /// Called by the client before it answers the encryption request.
+    fn join(&self, profile: &GameProfile, server_hash: &str) -> Result<()>;
+
+    /// Called by the server once it has the shared secret. Returns `None` if
+    /// the player never joined with this hash.
+    fn has_joined(&self, username: &str, server_hash: &str) -> Result<Option<GameProfile>>;
+}
+
+/// An in-memory session service. Clones share the same sessions, so one can
+/// be handed to a client and one to a server.
+#[derive(Debug, Clone, Default)]
+pub struct LocalSessionService {
+    joined: Arc<Mutex<HashMap<(String, String), GameProfile>>>,
+}
+
+impl SessionAuthenticator for LocalSessionService {
+    fn join(&self, profile: &GameProfile, server_hash: &str) -> Result<()> {
+        self.joined.lock().unwrap().insert(
+            (profile.name.clone(), server_hash.to_string()),
+            profile.clone(),
+        );
+        Ok(())
+    }
+
+    fn has_joined(&self, username: &str, server_hash: &str) -> Result<Option<GameProfile>> {
+        Ok(self
+            .joined
+            .lock()
+            .unwrap()
+            .remove(&(username.to_string(), server_hash.to_string())))
+    }
+}
+
+/// Minecraft's server hash: the SHA-1 digest read as a signed big-endian
+/// number and printed in hex, e.g. `-7c9d5b00...` for negative digests.
+pub fn server_hash(server_id: &str, shared_secret: &[u8], public_key: &[u8]) -> String {
+    let mut hasher = Sha1::new();
+    hasher.update(server_id.as_bytes());
+    hasher.update(shared_secret);
+    hasher.update(public_key);
+    BigInt::from_signed_bytes_be(&hasher.finalize()).to_str_radix(16)
+}
+
+fn encryption_error(e: impl std::fmt::Display) -> MooshroomError {
+    MooshroomError::Encryption(e.to_string())
+}
+
+/// The server's RSA key pair used to exchange the shared secret.
+pub struct ServerKey {
+    private: RsaPrivateKey,
+    public_der: Vec<u8>,
+}
+
+impl ServerKey {
+    /// Vanilla servers use 1024 bit keys.
+    pub fn generate(bits: usize) -> Result<Self> {
+        let private =
+            RsaPrivateKey::new(&mut rand::thread_rng(), bits).map_err(encryption_error)?;
+        let public_der = RsaPublicKey::from(&private)
+            .to_public_key_der()
+            .map_err(encryption_error)?
+            .into_vec();
+        Ok(Self {
+            private,
+            public_der,
+        })
+    }
+
+    pub fn public_key_der(&self) -> &[u8] {
+        &self.public_der
+    }
+
+    pub fn decrypt(&self, data: &[u8]) -> Result<Vec<u8>> {
+        self.private
+            .decrypt(Pkcs1v15Encrypt, data)
+            .map_err(encryption_error)
+    }
+}
+
+/// Builds the client's answer to `request` for the given shared secret.
+pub fn encryption_response(
+    request: &EncryptionRequest,
+    shared_secret: &[u8; 16],
+) -> Result<EncryptionResponse> {
+    let key = RsaPublicKey::from_public_key_der(&request.public_key).map_err(encryption_error)?;
+    let mut rng = rand::thread_rng();
+    let mut encrypt = |data: &[u8]| {
+        key.encrypt(&mut rng, Pkcs1v15Encrypt, data)
+            .map_err(encryption_error)
+    };
+    Ok(EncryptionResponse {
+        shared_secret: encrypt(shared_secret)?,
+        verification: EncryptionVerification::VerifyToken(encrypt(&request.verify_token)?),
+    })
+}
+
+/// Client side of the encryption exchange: joins the session, answers the
+/// request and enables encryption on `proto`.
+pub fn respond_to_encryption_request<T: Write>(
+    proto: &mut MooshroomProto<T>,
+    request: &EncryptionRequest,
+    profile: &GameProfile,
+    authenticator: &impl SessionAuthenticator,
+) -> Result<()> {
+    let shared_secret: [u8; 16] = rand::thread_rng().gen();
+    let hash = server_hash(&request.server_id, &shared_secret, &request.public_key);
+    authenticator.join(profile, &hash)?;
+
+    proto.write_packet(&encryption_response(request, &shared_secret)?)?;
+    proto.codec.enable_encryption(&shared_secret)
+}
+
+/// Server side of the encryption exchange for a player that sent
+/// `LoginStart` with `username`. On success the connection is encrypted and
+/// the authenticated profile is returned, ready to send as `LoginSuccess`.
+pub fn verify_online_login<T: Read + Write>(
+    proto: &mut MooshroomProto<T>,
+    key: &ServerKey,
+    username: &str,
+    authenticator: &impl SessionAuthenticator,
+) -> Result<GameProfile> {
+    let verify_token: [u8; 4] = rand::thread_rng().gen();
+    proto.write_packet(&EncryptionRequest {
+        server_id: String::new(),
+        public_key: key.public_der.clone(),
+        verify_token: verify_token.to_vec(),
+    })?;
+
+    let response: EncryptionResponse = proto.read_packet()?;
+    match &response.verification {
+        EncryptionVerification::VerifyToken(token) if key.decrypt(token)? == verify_token => {}
+        EncryptionVerification::VerifyToken(_) => {
+            return Err(MooshroomError::AuthenticationFailed(
+                "verify token mismatch".into(),
+            ))
+        }
+        EncryptionVerification::Signature(_) => {
+            return Err(MooshroomError::AuthenticationFailed(
+                "signed verify tokens are not supported".into(),
+            ))
+        }
+    }
+    let shared_secret = key.decrypt(&response.shared_secret)?;
+    proto.codec.enable_encryption(&shared_secret)?;
+
+    let hash = server_hash("", &shared_secret, &key.public_der);
+    authenticator
+        .has_joined(username, &hash)?
+        .ok_or_else(|| MooshroomError::AuthenticationFailed(format!("{} has not joined", username)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{TcpListener, TcpStream};
+
+    use super::*;
+    use crate::{
+        client::login::LoginStart,
+        proto::connection::{MooshroomConnection, Stage},
+    };
+
+    #[test]
+    fn test_server_hash() {
+        let hash = |name: &str| server_hash(name, &[], &[]);
+        assert_eq!(hash("Notch"), "4ed1f46bbe04bc756bcb17c0c7ce3e4632f06a48");
+        assert_eq!(hash("jeb_"), "-7c9d5b0044c130109a5d7b5fb5c317c02b4e28c1");
+        assert_eq!(hash("simon"), "88e16a1019277b15d58faf0541e11910eb756f6");
+    }
+
+    #[test]
+    fn test_online_login() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let sessions = LocalSessionService::default();
+        let profile = GameProfile {
+            uuid: uuid::Uuid::new_v4(),
+            name: "mooshroom".into(),
+            properties: Vec::new(),
+        };
+
+        let server_sessions = sessions.clone();
+        let server = std::thread::spawn(move || {
+            let key = ServerKey::generate(1024).unwrap();
+            let (stream, _) = listener.accept().unwrap();
+            let mut proto = MooshroomProto::new(stream);
+            let _: crate::client::handshake::Handshake = proto.read_packet().unwrap();
+            let start: LoginStart = proto.read_packet().unwrap();
+            let profile =
+                verify_online_login(&mut proto, &key, &start.name, &server_sessions).unwrap();
+            proto
+                .write_packet(&LoginSuccess::from(profile.clone()))
+                .unwrap();
+            profile
+        });
+
+        let mut conn = MooshroomConnection::new(TcpStream::connect(addr).unwrap());
+        conn.handshake_online(&profile, &sessions).unwrap();
+        assert!(matches!(conn.stage(), Stage::Play(uuid) if *uuid == profile.uuid));
+        assert_eq!(server.join().unwrap(), profile);
+    }
+}
@@ -0,0 +1,279 @@
+use std::{
+    collections::VecDeque,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use rand::Rng;
+use sha2::{Digest, Sha256};
+
+use crate::{
+    client::chat::{ArgumentSignature, ChatCommand, ChatMessage, MessageAcknowledgment},
+    core::error::Result,
+    server::play::world::PlayerChatMessage,
+    shared::PreviousMessage,
+};
+
+/// How many seen messages are sent along with each message.
+pub const MAX_LAST_SEEN: usize = 5;
+
+/// The vanilla client acknowledges messages once this many are pending.
+pub const ACKNOWLEDGE_AFTER: u32 = 64;
+
+/// Separates the parts of a message body when hashing it.
+const HASH_SEPARATOR: u8 = 70;
+
+/// Signs chat with the player's profile key.
+pub trait ChatSigner {
+    fn sign(&self, data: &[u8]) -> Result<Vec<u8>>;
+}
+
+/// The signed part of a chat message.
+#[derive(Debug, Clone, Copy)]
+pub struct MessageBody<'a> {
+    pub content: &'a str,
+    /// The decorated message as JSON, when the server previewed it.
+    pub decorated: Option<&'a str>,
+    /// Milliseconds since the unix epoch.
+    pub timestamp: i64,
+    pub salt: i64,
+    pub last_seen: &'a [PreviousMessage],
+}
+
+impl MessageBody<'_> {
+    pub fn hash(&self) -> [u8; 32] {
+        let mut h = Sha256::new();
+        h.update(self.salt.to_be_bytes());
+        h.update((self.timestamp.div_euclid(1000)).to_be_bytes());
+        h.update(self.content.as_bytes());
+        h.update([HASH_SEPARATOR]);
+        if let Some(decorated) = self.decorated {
+            h.update(decorated.as_bytes());
+        }
+        for seen in self.last_seen {
+            h.update([HASH_SEPARATOR]);
+            h.update(seen.sender.as_bytes());
+            h.update(&seen.signature);
+        }
+        h.finalize().into()
+    }
+}
+
+/// The data covered by a message header signature: the previous header
+/// signature of the same sender, their UUID and the body hash.
+pub fn header_data(previous: Option<&[u8]>, sender: uuid::Uuid, body_hash: &[u8]) -> Vec<u8> {
+    let mut data = Vec::with_capacity(previous.map_or(0, <[u8]>::len) + 16 + body_hash.len());
+    data.extend_from_slice(previous.unwrap_or_default());
+    data.extend_from_slice(sender.as_bytes());
+    data.extend_from_slice(body_hash);
+    data
+}
+
+/// Builds outgoing chat packets and tracks the messages the server expects
+/// us to acknowledge. Without a signer, messages are sent unsigned, which
+/// only offline servers accept.
+#[derive(Default)]
+pub struct ChatSession {
+    signer: Option<Box<dyn ChatSigner>>,
+    previous_signature: Option<Vec<u8>>,
+    /// Newest first.
+    last_seen: VecDeque<PreviousMessage>,
+    last_received: Option<PreviousMessage>,
+    pending: u32,
+}
+
+impl ChatSession {
+    pub fn set_signer(&mut self, signer: impl ChatSigner + 'static) {
+        self.signer = Some(Box::new(signer));
+        self.previous_signature = None;
+    }
+
+    pub fn is_signed(&self) -> bool {
+        self.signer.is_some()
+    }
+
+    /// Records a received message. Returns an acknowledgment when enough
+    /// messages are pending that one should be sent.
+    pub fn observe(&mut self, p: &PlayerChatMessage) -> Option<MessageAcknowledgment> {
+        if p.header_signature.is_empty() {
+            return None;
+        }
+        let entry = PreviousMessage {
+            sender: p.sender,
+            signature: p.header_signature.clone(),
+        };
+        if let Some(previous) = self.last_received.replace(entry) {
+            self.push_seen(previous);
+        }
+        self.pending += 1;
+        (self.pending > ACKNOWLEDGE_AFTER).then(|| self.acknowledgment())
+    }
+
+    pub fn acknowledgment(&mut self) -> MessageAcknowledgment {
+        let (last_seen_messages, last_received) = self.take_update();
+        MessageAcknowledgment {
+            last_seen_messages,
+            last_received,
+        }
+    }
+
+    pub fn chat_message(&mut self, sender: uuid::Uuid, message: &str) -> Result<ChatMessage> {
+        let (timestamp, salt) = now_and_salt();
+        let (last_seen_messages, last_received) = self.take_update();
+        let signature = self.sign(
+            sender,
+            MessageBody {
+                content: message,
+                decorated: None,
+                timestamp,
+                salt,
+                last_seen: &last_seen_messages,
+            },
+        )?;
+        Ok(ChatMessage {
+            message: message.into(),
+            timestamp,
+            salt,
+            signature,
+            signed_preview: false,
+            last_seen_messages,
+            last_received,
+        })
+    }
+
+    /// `command` is without the leading slash. `arguments` are the names and
+    /// values of the command's message arguments, such as the message of
+    /// `/msg`, which get signed.
+    pub fn chat_command(
+        &mut self,
+        sender: uuid::Uuid,
+        command: &str,
+        arguments: &[(&str, &str)],
+    ) -> Result<ChatCommand> {
+        let (timestamp, salt) = now_and_salt();
+        let (last_seen_messages, last_received) = self.take_update();
+        let mut argument_signatures = Vec::new();
+        if self.is_signed() {
+            for (name, value) in arguments {
+                let body = MessageBody {
+                    content: value,
+                    decorated: None,
+                    timestamp,
+                    salt,
+                    last_seen: &last_seen_messages,
+                };
+                argument_signatures.push(ArgumentSignature {
+                    argument_name: name.to_string(),
+                    signature: self.sign(sender, body)?,
+                });
+            }
+        }
+        Ok(ChatCommand {
+            command: command.into(),
+            timestamp,
+            salt,
+            argument_signatures,
+            signed_preview: false,
+            last_seen_messages,
+            last_received,
+        })
+    }
+
+    /// Signs `body` as the next message in our chain. Returns an empty
+    /// signature when there is no signer.
+    fn sign(&mut self, sender: uuid::Uuid, body: MessageBody) -> Result<Vec<u8>> {
+        let Some(signer) = &self.signer else {
+            return Ok(Vec::new());
+        };
+        let data = header_data(self.previous_signature.as_deref(), sender, &body.hash());
+        let signature = signer.sign(&data)?;
+        self.previous_signature = Some(signature.clone());
+        Ok(signature)
+    }
+
+    /// The seen messages to send, after which the last received one counts
+    /// as seen.
+    fn take_update(&mut self) -> (Vec<PreviousMessage>, Option<PreviousMessage>) {
+        let seen = self.last_seen.iter().cloned().collect();
+        let received = self.last_received.take();
+        if let Some(r) = &received {
+            self.push_seen(r.clone());
+        }
+        self.pending = 0;
+        (seen, received)
+    }
+
+    fn push_seen(&mut self, entry: PreviousMessage) {
+        self.last_seen.retain(|e| e.sender != entry.sender);
+        self.last_seen.push_front(entry);
+        self.last_seen.truncate(MAX_LAST_SEEN);
+    }
+}
+
+fn now_and_salt() -> (i64, i64) {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis() as i64);
+    (timestamp, rand::thread_rng().gen())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Reverse;
+    impl ChatSigner for Reverse {
+        fn sign(&self, data: &[u8]) -> Result<Vec<u8>> {
+            Ok(data.iter().rev().copied().collect())
+        }
+    }
+
+    fn received(sender: u128, signature: u8) -> PlayerChatMessage {
+        PlayerChatMessage {
+            sender: uuid::Uuid::from_u128(sender),
+            header_signature: vec![signature],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_unsigned_chat() {
+        let mut chat = ChatSession::default();
+        let p = chat.chat_message(uuid::Uuid::nil(), "hello").unwrap();
+        assert!(p.signature.is_empty());
+        assert!(p.last_received.is_none());
+
+        let p = chat
+            .chat_command(uuid::Uuid::nil(), "msg Steve hi", &[("message", "hi")])
+            .unwrap();
+        assert!(p.argument_signatures.is_empty());
+    }
+
+    #[test]
+    fn test_last_seen_and_chain() {
+        let mut chat = ChatSession::default();
+        chat.set_signer(Reverse);
+        let me = uuid::Uuid::from_u128(9);
+
+        chat.observe(&received(1, 1));
+        chat.observe(&received(2, 2));
+        chat.observe(&received(1, 3));
+        let first = chat.chat_message(me, "hi").unwrap();
+        assert_eq!(first.last_received.unwrap().signature, vec![3]);
+        assert_eq!(
+            first
+                .last_seen_messages
+                .iter()
+                .map(|m| m.signature[0])
+                .collect::<Vec<_>>(),
+            vec![2, 1]
+        );
+
+        // The second message's header covers the first one's signature.
+        let second = chat.chat_message(me, "again").unwrap();
+        let mut data = second.signature.clone();
+        data.reverse();
+        assert!(data.starts_with(&first.signature));
+        assert_eq!(second.last_seen_messages.len(), 2);
+        assert_eq!(second.last_seen_messages[0].signature, vec![3]);
+    }
+}
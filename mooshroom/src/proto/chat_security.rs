@@ -0,0 +1,265 @@
+use std::collections::HashMap;
+
+use base64::Engine;
+use rsa::{
+    pkcs1::DecodeRsaPrivateKey,
+    pkcs8::{DecodePrivateKey, DecodePublicKey, EncodePublicKey},
+    Pkcs1v15Sign,
+    RsaPrivateKey,
+    RsaPublicKey,
+};
+use sha2::{Digest, Sha256};
+
+use super::chat::{header_data, ChatSigner, MessageBody};
+use crate::{
+    core::error::{MooshroomError, Result},
+    server::play::{
+        population::{PlayerAction, PlayerInfo},
+        world::PlayerChatMessage,
+    },
+    shared::SignatureData,
+};
+
+fn key_error(e: impl std::fmt::Display) -> MooshroomError {
+    MooshroomError::InvalidProfileKey(e.to_string())
+}
+
+/// The player's chat signing key pair.
+pub struct ProfileKeys {
+    private: RsaPrivateKey,
+    public_der: Vec<u8>,
+    /// Milliseconds since the unix epoch.
+    pub expires_at: u64,
+    /// Mojang's signature of the public key. Empty for generated keys, which
+    /// servers enforcing secure chat reject.
+    pub key_signature: Vec<u8>,
+}
+
+impl ProfileKeys {
+    /// Generates a key pair. Mojang's keys are 2048 bits.
+    pub fn generate(bits: usize, expires_at: u64) -> Result<Self> {
+        let private = RsaPrivateKey::new(&mut rand::thread_rng(), bits).map_err(key_error)?;
+        Self::from_private_key(private, expires_at, Vec::new())
+    }
+
+    /// Loads the private key as returned by the player certificates
+    /// endpoint. Both PKCS#8 and PKCS#1 keys are accepted.
+    pub fn from_pem(
+        private_key_pem: &str,
+        expires_at: u64,
+        key_signature: Vec<u8>,
+    ) -> Result<Self> {
+        let body: String = private_key_pem
+            .lines()
+            .filter(|l| !l.starts_with("-----"))
+            .collect();
+        let der = base64::engine::general_purpose::STANDARD
+            .decode(body.trim())
+            .map_err(key_error)?;
+        let private = RsaPrivateKey::from_pkcs8_der(&der)
+            .or_else(|_| RsaPrivateKey::from_pkcs1_der(&der))
+            .map_err(key_error)?;
+        Self::from_private_key(private, expires_at, key_signature)
+    }
+
+    fn from_private_key(
+        private: RsaPrivateKey,
+        expires_at: u64,
+        key_signature: Vec<u8>,
+    ) -> Result<Self> {
+        let public_der = RsaPublicKey::from(&private)
+            .to_public_key_der()
+            .map_err(key_error)?
+            .into_vec();
+        Ok(Self {
+            private,
+            public_der,
+            expires_at,
+            key_signature,
+        })
+    }
+
+    pub fn public_key_der(&self) -> &[u8] {
+        &self.public_der
+    }
+
+    /// The key data sent in `LoginStart`.
+    pub fn signature_data(&self) -> SignatureData {
+        SignatureData {
+            timestamp: self.expires_at,
+            public_key: self.public_der.clone(),
+            signature: self.key_signature.clone(),
+        }
+    }
+}
+
+impl ChatSigner for ProfileKeys {
+    fn sign(&self, data: &[u8]) -> Result<Vec<u8>> {
+        self.private
+            .sign(Pkcs1v15Sign::new::<Sha256>(), &Sha256::digest(data))
+            .map_err(key_error)
+    }
+}
+
+/// The outcome of checking a received chat message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MessageStatus {
+    Verified,
+    /// Normal on offline servers.
+    Unsigned,
+    /// No key is known for the sender.
+    UnknownSender,
+    /// The sender's key had expired when the message was sent.
+    ExpiredKey,
+    /// The signature does not match the message.
+    Tampered,
+    /// The signature is valid, but the message does not follow the sender's
+    /// previous one, so messages were dropped or reordered.
+    BrokenChain,
+}
+
+struct Sender {
+    key: RsaPublicKey,
+    expires_at: u64,
+    last_header: Option<Vec<u8>>,
+}
+
+/// Checks received chat messages against the senders' keys, which are learnt
+/// from `PlayerInfo` packets.
+#[derive(Default)]
+pub struct ChatVerifier {
+    senders: HashMap<uuid::Uuid, Sender>,
+}
+
+impl ChatVerifier {
+    pub fn add_player(&mut self, uuid: uuid::Uuid, data: &SignatureData) -> Result<()> {
+        let key = RsaPublicKey::from_public_key_der(&data.public_key).map_err(key_error)?;
+        self.senders.insert(
+            uuid,
+            Sender {
+                key,
+                expires_at: data.timestamp,
+                last_header: None,
+            },
+        );
+        Ok(())
+    }
+
+    pub fn remove_player(&mut self, uuid: &uuid::Uuid) {
+        self.senders.remove(uuid);
+    }
+
+    /// Tracks players joining and leaving. Players with invalid keys are
+    /// left unknown.
+    pub fn observe(&mut self, p: &PlayerInfo) {
+        match &p.0 {
+            PlayerAction::AddPlayer(players) => {
+                for player in players {
+                    if let Some(data) = &player.action.signature_data {
+                        let _ = self.add_player(player.uuid, data);
+                    }
+                }
+            }
+            PlayerAction::RemovePlayer(uuids) => uuids.iter().for_each(|u| self.remove_player(u)),
+            _ => {}
+        }
+    }
+
+    pub fn verify(&mut self, p: &PlayerChatMessage) -> MessageStatus {
+        if p.header_signature.is_empty() {
+            return MessageStatus::Unsigned;
+        }
+        let Some(sender) = self.senders.get_mut(&p.sender) else {
+            return MessageStatus::UnknownSender;
+        };
+        if p.timestamp as u64 > sender.expires_at {
+            return MessageStatus::ExpiredKey;
+        }
+
+        let body = MessageBody {
+            content: &p.plain_message,
+            decorated: p.formatted_message.as_deref(),
+            timestamp: p.timestamp,
+            salt: p.salt,
+            last_seen: &p.previous_messages,
+        };
+        let data = header_data(p.message_signature.as_deref(), p.sender, &body.hash());
+        let valid = sender
+            .key
+            .verify(
+                Pkcs1v15Sign::new::<Sha256>(),
+                &Sha256::digest(data),
+                &p.header_signature,
+            )
+            .is_ok();
+        if !valid {
+            return MessageStatus::Tampered;
+        }
+
+        let last = sender.last_header.replace(p.header_signature.clone());
+        match last {
+            Some(last) if p.message_signature.as_ref() != Some(&last) => MessageStatus::BrokenChain,
+            _ => MessageStatus::Verified,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rsa::pkcs8::{EncodePrivateKey, LineEnding};
+
+    use super::*;
+    use crate::{client::chat::ChatMessage, proto::chat::ChatSession};
+
+    fn received(
+        sender: uuid::Uuid,
+        m: ChatMessage,
+        previous: Option<Vec<u8>>,
+    ) -> PlayerChatMessage {
+        PlayerChatMessage {
+            message_signature: previous,
+            sender,
+            header_signature: m.signature,
+            plain_message: m.message,
+            timestamp: m.timestamp,
+            salt: m.salt,
+            previous_messages: m.last_seen_messages,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_verify_signed_chat() {
+        let keys = ProfileKeys::generate(1024, u64::MAX).unwrap();
+        let pem = keys.private.to_pkcs8_pem(LineEnding::LF).unwrap();
+        let loaded = ProfileKeys::from_pem(&pem, u64::MAX, Vec::new()).unwrap();
+        assert_eq!(loaded.public_key_der(), keys.public_key_der());
+        let steve = uuid::Uuid::new_v4();
+        let mut verifier = ChatVerifier::default();
+        verifier.add_player(steve, &keys.signature_data()).unwrap();
+        let mut session = ChatSession::default();
+        session.set_signer(keys);
+
+        let first = session.chat_message(steve, "hello").unwrap();
+        let second = session.chat_message(steve, "world").unwrap();
+        let third = session.chat_message(steve, "!").unwrap();
+        let first = received(steve, first, None);
+        let second_signature = Some(second.signature.clone());
+        let mut third = received(steve, third, second_signature);
+
+        let mut tampered = first.clone();
+        tampered.plain_message = "goodbye".into();
+        assert_eq!(verifier.verify(&tampered), MessageStatus::Tampered);
+        assert_eq!(verifier.verify(&first), MessageStatus::Verified);
+        // The second message never arrived.
+        assert_eq!(verifier.verify(&third), MessageStatus::BrokenChain);
+
+        third.message_signature = Some(first.header_signature.clone());
+        assert_eq!(verifier.verify(&third), MessageStatus::Tampered);
+        third.header_signature.clear();
+        assert_eq!(verifier.verify(&third), MessageStatus::Unsigned);
+        third.header_signature = vec![1];
+        third.sender = uuid::Uuid::nil();
+        assert_eq!(verifier.verify(&third), MessageStatus::UnknownSender);
+    }
+}
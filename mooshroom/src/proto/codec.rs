@@ -1,5 +1,16 @@
-use std::io::Read;
+use std::{
+    io::Read,
+    sync::{
+        atomic::{AtomicI32, Ordering},
+        Arc,
+        Mutex,
+    },
+};
 
+use aes::{
+    cipher::{inout::InOutBuf, BlockDecryptMut, BlockEncryptMut, KeyIvInit},
+    Aes128,
+};
 use bytes::BytesMut;
 use flate2::{
     read::{ZlibDecoder, ZlibEncoder},
@@ -31,8 +42,84 @@ pub struct PacketData<'a> {
     pub body: PacketBody<'a>,
 }
 
+/// A packet that has been framed but not decoded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RawPacket {
+    pub id: VarInt,
+    pub body: BytesMut,
+}
+
+impl RawPacket {
+    pub fn new(id: VarInt, body: impl Into<BytesMut>) -> Self {
+        Self {
+            id,
+            body: body.into(),
+        }
+    }
+
+    pub fn from_packet<const PV: usize, P: MooshroomPacket<PV>>(packet: &P) -> Result<Self> {
+        let mut body = Vec::new();
+        packet.write(&mut body)?;
+        Ok(Self::new(P::PACKET_ID, &body[..]))
+    }
+
+    pub fn from_one_of<const PV: usize, P: MooshroomCollection<PV>>(packet: &P) -> Result<Self> {
+        let mut body = Vec::new();
+        packet.write_one_of(&mut body)?;
+        Ok(Self::new(packet.variant_id(), &body[..]))
+    }
+
+    pub fn decode<const PV: usize, P: MooshroomPacket<PV>>(&self) -> Result<P> {
+        if P::PACKET_ID != self.id {
+            return Err(MooshroomError::UnexpectedPacket(P::PACKET_ID.0, self.id.0));
+        }
+        P::read(&mut self.body.as_ref())
+    }
+
+    pub fn decode_one_of<const PV: usize, P: MooshroomCollection<PV>>(&self) -> Result<P> {
+        P::read_one_of(self.id, &mut self.body.as_ref())
+    }
+}
+
+impl<'a> From<PacketData<'a>> for RawPacket {
+    fn from(data: PacketData<'a>) -> Self {
+        let body = match data.body {
+            PacketBody::Owned(o) => o,
+            PacketBody::Borrowed(b) => BytesMut::from(b),
+        };
+        Self {
+            id: data.packet_id,
+            body,
+        }
+    }
+}
+
+/// The largest uncompressed packet vanilla accepts.
+const MAX_DECOMPRESSED_SIZE: usize = 8 * 1024 * 1024;
+
+type Encryptor = cfb8::Encryptor<Aes128>;
+type Decryptor = cfb8::Decryptor<Aes128>;
+
+/// Connection settings shared by both halves of a split codec.
+#[derive(Debug)]
+struct CodecSettings {
+    compression: AtomicI32,
+    shared_secret: Mutex<Option<[u8; 16]>>,
+}
+
+impl Default for CodecSettings {
+    fn default() -> Self {
+        Self {
+            compression: AtomicI32::new(-1),
+            shared_secret: Mutex::new(None),
+        }
+    }
+}
+
 pub struct MooshroomCodec<const PV: usize> {
-    compression: Option<i32>,
+    settings: Arc<CodecSettings>,
+    encryptor: Option<Encryptor>,
+    decryptor: Option<Decryptor>,
     write_buffer: Vec<u8>,
     compress_buffer: Vec<u8>,
     rx_buffer: BytesMut,
@@ -40,24 +127,75 @@ pub struct MooshroomCodec<const PV: usize> {
 
 impl<const PV: usize> MooshroomCodec<PV> {
     pub fn new() -> Self {
+        Self::with_settings(Default::default())
+    }
+
+    fn with_settings(settings: Arc<CodecSettings>) -> Self {
         Self {
             write_buffer: Vec::new(),
             compress_buffer: Vec::new(),
-            compression: None,
+            settings,
+            encryptor: None,
+            decryptor: None,
 
             rx_buffer: BytesMut::new(),
         }
     }
 
+    /// Splits the codec into a reading and a writing half. Settings such as
+    /// compression changed on either half apply to both. If encryption is
+    /// already on, the writer takes over the encryptor so its keystream
+    /// carries on where it left off.
+    pub fn split(mut self) -> (Self, Self) {
+        let mut writer = Self::with_settings(self.settings.clone());
+        writer.encryptor = self.encryptor.take();
+        (self, writer)
+    }
+
     pub const fn protocal_version(&self) -> i32 {
         PV as i32
     }
 
     pub fn set_compression(&mut self, th: i32) {
-        if th < 0 {
-            self.compression = None;
-        } else {
-            self.compression = Some(th);
+        self.settings
+            .compression
+            .store(th.max(-1), Ordering::SeqCst);
+    }
+
+    pub fn compression(&self) -> Option<i32> {
+        let th = self.settings.compression.load(Ordering::SeqCst);
+        (th >= 0).then_some(th)
+    }
+
+    /// Enables AES/CFB8 encryption with the shared secret as both key and IV.
+    /// Bytes already added but not yet read are assumed to be encrypted.
+    pub fn enable_encryption(&mut self, shared_secret: &[u8]) -> Result<()> {
+        let key: [u8; 16] = shared_secret
+            .try_into()
+            .map_err(|_| MooshroomError::InvalidSharedSecret)?;
+        *self.settings.shared_secret.lock().unwrap() = Some(key);
+        self.sync_encryption();
+        if let Some(decryptor) = &mut self.decryptor {
+            let (blocks, _) = InOutBuf::from(&mut self.rx_buffer[..]).into_chunks();
+            decryptor.decrypt_blocks_inout_mut(blocks);
+        }
+        Ok(())
+    }
+
+    pub fn is_encrypted(&self) -> bool {
+        self.settings.shared_secret.lock().unwrap().is_some()
+    }
+
+    /// Picks up encryption enabled on the other half of a split codec. A half
+    /// that already has either cipher keeps it, since rebuilding would
+    /// restart the keystream.
+    fn sync_encryption(&mut self) {
+        if self.encryptor.is_some() || self.decryptor.is_some() {
+            return;
+        }
+        if let Some(key) = *self.settings.shared_secret.lock().unwrap() {
+            self.encryptor = Some(Encryptor::new(&key.into(), &key.into()));
+            self.decryptor = Some(Decryptor::new(&key.into(), &key.into()));
         }
     }
     pub fn finalize_compressed(&mut self, threshold: i32) -> Result<Vec<u8>> {
@@ -107,23 +245,44 @@ impl<const PV: usize> MooshroomCodec<PV> {
         Ok(buffer)
     }
 
-    pub fn encode<T: MooshroomPacket<PV>>(&mut self, packet: &T) -> Result<Vec<u8>> {
-        self.write_buffer.clear();
+    fn finalize(&mut self) -> Result<Vec<u8>> {
         self.compress_buffer.clear();
-        T::PACKET_ID.write_proto::<PV>(&mut self.write_buffer)?;
-        packet.write(&mut self.write_buffer)?;
-
-        let buffer = if let Some(c) = self.compression {
+        let mut buffer = if let Some(c) = self.compression() {
             self.finalize_compressed(c)?
         } else {
             self.finalize_uncompressed()?
         };
-
+        self.sync_encryption();
+        if let Some(encryptor) = &mut self.encryptor {
+            let (blocks, _) = InOutBuf::from(&mut buffer[..]).into_chunks();
+            encryptor.encrypt_blocks_inout_mut(blocks);
+        }
         Ok(buffer)
     }
 
+    pub fn encode<T: MooshroomPacket<PV>>(&mut self, packet: &T) -> Result<Vec<u8>> {
+        self.write_buffer.clear();
+        T::PACKET_ID.write_proto::<PV>(&mut self.write_buffer)?;
+        packet.write(&mut self.write_buffer)?;
+        self.finalize()
+    }
+
+    /// Frames an already encoded packet using this codec's compression settings.
+    pub fn encode_raw(&mut self, packet: &RawPacket) -> Result<Vec<u8>> {
+        self.write_buffer.clear();
+        packet.id.write_proto::<PV>(&mut self.write_buffer)?;
+        self.write_buffer.extend_from_slice(&packet.body);
+        self.finalize()
+    }
+
     pub fn add_bytes(&mut self, bytes: &[u8]) {
+        self.sync_encryption();
+        let start = self.rx_buffer.len();
         self.rx_buffer.extend(bytes);
+        if let Some(decryptor) = &mut self.decryptor {
+            let (blocks, _) = InOutBuf::from(&mut self.rx_buffer[start..]).into_chunks();
+            decryptor.decrypt_blocks_inout_mut(blocks);
+        }
     }
 
     pub fn peek_packet(&mut self) -> Option<(VarInt, usize)> {
@@ -137,7 +296,7 @@ impl<const PV: usize> MooshroomCodec<PV> {
         }
     }
 
-    pub fn read_packet_data(&mut self) -> Result<Option<PacketData>> {
+    pub fn read_packet_data(&mut self) -> Result<Option<PacketData<'_>>> {
         let (length, lenght_bytes_n) = match self.peek_packet() {
             Some((l, n)) => (l, n),
             None => return Ok(None),
@@ -152,7 +311,7 @@ impl<const PV: usize> MooshroomCodec<PV> {
             spl
         };
 
-        let decompressed_size = if self.compression.is_some() {
+        let decompressed_size = if self.compression().is_some() {
             let (decompressed_size, decompressed_size_n) = VarInt::read_with_size::<PV>(&raw_data)?;
             raw_data = raw_data.split_off(decompressed_size_n);
             if decompressed_size.0 > 0 {
@@ -165,12 +324,22 @@ impl<const PV: usize> MooshroomCodec<PV> {
         };
 
         if let Some(decompressed_size) = decompressed_size {
+            // The size comes from the peer, so refuse anything larger than
+            // vanilla accepts before inflating, and stop inflating there in
+            // case the size lied.
+            if decompressed_size > MAX_DECOMPRESSED_SIZE {
+                return Err(MooshroomError::InvalidLength(decompressed_size as i32));
+            }
             self.compress_buffer.clear();
             self.compress_buffer.reserve_exact(decompressed_size);
 
             let decompressed_bytes = {
-                let mut decompress = ZlibDecoder::new(&raw_data[..]);
-                decompress.read_to_end(&mut self.compress_buffer)?;
+                ZlibDecoder::new(&raw_data[..])
+                    .take(decompressed_size as u64 + 1)
+                    .read_to_end(&mut self.compress_buffer)?;
+                if self.compress_buffer.len() != decompressed_size {
+                    return Err(MooshroomError::InvalidLength(decompressed_size as i32));
+                }
                 self.compress_buffer.as_ref()
             };
 
@@ -190,6 +359,10 @@ impl<const PV: usize> MooshroomCodec<PV> {
         }
     }
 
+    pub fn read_raw(&mut self) -> Result<Option<RawPacket>> {
+        Ok(self.read_packet_data()?.map(RawPacket::from))
+    }
+
     pub fn read_packet<'a, P: MooshroomPacket<PV>>(&mut self) -> Result<Option<P>> {
         let data = match self.read_packet_data()? {
             Some(e) => e,
@@ -225,3 +398,133 @@ impl<const PV: usize> MooshroomCodec<PV> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::{
+        login::{LoginStage, SetCompression},
+        play::metadata::KeepAlive,
+    };
+
+    const PV: usize = DEFAULT_PROTOCAL_VERSION;
+
+    #[test]
+    fn test_raw_packet_reframe() {
+        let mut upstream = MooshroomCodec::<PV>::new();
+        upstream.set_compression(0);
+        let bytes = upstream.encode(&KeepAlive(42)).unwrap();
+
+        let mut proxy_rx = MooshroomCodec::<PV>::new();
+        proxy_rx.set_compression(0);
+        proxy_rx.add_bytes(&bytes);
+        let raw = proxy_rx.read_raw().unwrap().unwrap();
+        assert_eq!(raw.id, 0x20);
+
+        let mut proxy_tx = MooshroomCodec::<PV>::new();
+        let reframed = proxy_tx.encode_raw(&raw).unwrap();
+        assert_eq!(
+            reframed,
+            MooshroomCodec::<PV>::new().encode(&KeepAlive(42)).unwrap()
+        );
+
+        let mut downstream = MooshroomCodec::<PV>::new();
+        downstream.add_bytes(&reframed);
+        let p: KeepAlive = downstream.read_packet().unwrap().unwrap();
+        assert_eq!(p.0, 42);
+    }
+
+    #[test]
+    fn test_raw_packet_decode() {
+        let raw = RawPacket::from_packet::<PV, _>(&SetCompression {
+            threshold: 256.into(),
+        })
+        .unwrap();
+
+        let p: SetCompression = raw.decode::<PV, _>().unwrap();
+        assert_eq!(p.threshold, 256);
+        assert!(matches!(
+            raw.decode_one_of::<PV, LoginStage>().unwrap(),
+            LoginStage::SetCompression(_)
+        ));
+        assert!(raw.decode::<PV, KeepAlive>().is_err());
+    }
+
+    #[test]
+    fn test_encrypted_round_trip() {
+        let secret = [7; 16];
+        let mut client = MooshroomCodec::<PV>::new();
+        let mut server = MooshroomCodec::<PV>::new();
+        client.enable_encryption(&secret).unwrap();
+
+        let first = client.encode(&KeepAlive(1)).unwrap();
+        let second = client.encode(&KeepAlive(2)).unwrap();
+        assert_ne!(
+            first,
+            MooshroomCodec::<PV>::new().encode(&KeepAlive(1)).unwrap()
+        );
+
+        // Bytes that arrive before the switch are decrypted when it happens.
+        server.add_bytes(&first[..3]);
+        server.enable_encryption(&secret).unwrap();
+        server.add_bytes(&first[3..]);
+        server.add_bytes(&second);
+        let a: KeepAlive = server.read_packet().unwrap().unwrap();
+        let b: KeepAlive = server.read_packet().unwrap().unwrap();
+        assert_eq!((a.0, b.0), (1, 2));
+        assert!(server.enable_encryption(&[0; 8]).is_err());
+    }
+
+    #[test]
+    fn test_split_after_encryption() {
+        let secret = [7; 16];
+        let mut client = MooshroomCodec::<PV>::new();
+        let mut server = MooshroomCodec::<PV>::new();
+        client.enable_encryption(&secret).unwrap();
+        server.enable_encryption(&secret).unwrap();
+
+        server.add_bytes(&client.encode(&KeepAlive(1)).unwrap());
+        let (mut reader, mut writer) = client.split();
+        server.add_bytes(&writer.encode(&KeepAlive(2)).unwrap());
+        let a: KeepAlive = server.read_packet().unwrap().unwrap();
+        let b: KeepAlive = server.read_packet().unwrap().unwrap();
+        assert_eq!((a.0, b.0), (1, 2));
+
+        // The reader keeps its decryptor.
+        reader.add_bytes(&server.encode(&KeepAlive(3)).unwrap());
+        let c: KeepAlive = reader.read_packet().unwrap().unwrap();
+        assert_eq!(c.0, 3);
+    }
+
+    /// Frames `body` as a compressed packet claiming to inflate to `size`.
+    fn compressed_frame(size: i32, body: &[u8]) -> Vec<u8> {
+        let mut data = Vec::new();
+        VarInt(size).write_proto::<PV>(&mut data).unwrap();
+        ZlibEncoder::new(body, Compression::default())
+            .read_to_end(&mut data)
+            .unwrap();
+        let mut frame = Vec::new();
+        VarInt(data.len() as i32)
+            .write_proto::<PV>(&mut frame)
+            .unwrap();
+        frame.extend(data);
+        frame
+    }
+
+    #[test]
+    fn test_decompressed_size_is_enforced() {
+        let read = |frame: Vec<u8>| {
+            let mut codec = MooshroomCodec::<PV>::new();
+            codec.set_compression(0);
+            codec.add_bytes(&frame);
+            codec.read_raw().map(|raw| raw.map(|raw| raw.body.len()))
+        };
+        let body = [0x20, 0, 0, 0, 0, 0, 0, 0, 42];
+        assert_eq!(read(compressed_frame(9, &body)).unwrap(), Some(8));
+
+        let bomb = vec![0; MAX_DECOMPRESSED_SIZE + 1];
+        assert!(read(compressed_frame(16, &bomb)).is_err());
+        assert!(read(compressed_frame(MAX_DECOMPRESSED_SIZE as i32 + 1, &bomb)).is_err());
+        assert!(read(compressed_frame(16, &body)).is_err());
+    }
+}
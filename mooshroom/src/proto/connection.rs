@@ -1,18 +1,53 @@
-use std::net::TcpStream;
+use std::{
+    fmt::Debug,
+    net::{SocketAddr, TcpStream},
+    time::Duration,
+};
 
-use super::MooshroomProto;
+use super::{
+    auth::{self, GameProfile, SessionAuthenticator},
+    chat::ChatSession,
+    interceptor::Interceptor,
+    login_plugin::LoginPlugins,
+    movement::{MovementPacket, MovementTracker},
+    plugin_channels::PluginChannels,
+    MooshroomProto,
+};
 use crate::{
     client::{
         handshake::{Handshake, HandshakeState},
         login::LoginStart,
-        metadata::KeepAliveResponse,
-        player,
+        metadata::{
+            ClientInformation,
+            KeepAliveResponse,
+            Pong,
+            ResourcePackResponse,
+            ResourcePackResult,
+        },
+        player::{self, ConfirmTeleportation},
+        world::{
+            BlockFace,
+            Hand,
+            Interact,
+            InteractAction,
+            PlayerAction,
+            PlayerActionStatus,
+            SwingArm,
+            UseItem,
+            UseItemOn,
+        },
+    },
+    core::{
+        error::{MooshroomError, Result},
+        io::{MooshroomPacket, DEFAULT_PROTOCAL_VERSION},
+        primitives::Position,
+        varint::VarInt,
     },
-    core::error::Result,
     server::{
-        login::{LoginStage, LoginSuccess},
+        login::{Disconnect, EncryptionRequest, LoginStage, LoginSuccess},
         play::PlayStage,
     },
+    shared::SignatureData,
 };
 
 pub enum Stage {
@@ -21,16 +56,159 @@ pub enum Stage {
     Play(uuid::Uuid),
 }
 
+/// Who to log in as and what to put in the handshake.
+#[derive(Debug, Clone)]
+pub struct LoginOptions {
+    username: String,
+    uuid: Option<uuid::Uuid>,
+    address: Option<(String, u16)>,
+    protocol_version: Option<i32>,
+    sig_data: Option<SignatureData>,
+}
+
+impl Default for LoginOptions {
+    fn default() -> Self {
+        Self::new("mooshroom")
+    }
+}
+
+impl LoginOptions {
+    pub fn new(username: impl Into<String>) -> Self {
+        Self {
+            username: username.into(),
+            uuid: None,
+            address: None,
+            protocol_version: None,
+            sig_data: None,
+        }
+    }
+
+    pub fn username(mut self, username: impl Into<String>) -> Self {
+        self.username = username.into();
+        self
+    }
+
+    pub fn uuid(mut self, uuid: uuid::Uuid) -> Self {
+        self.uuid = Some(uuid);
+        self
+    }
+
+    /// The hostname and port sent in the handshake. Proxies and virtual hosts
+    /// route on these, so they should be what the user connected to rather
+    /// than the resolved address. Defaults to the peer's IP and port.
+    pub fn address(mut self, hostname: impl Into<String>, port: u16) -> Self {
+        self.address = Some((hostname.into(), port));
+        self
+    }
+
+    /// Defaults to the protocol version of the codec.
+    pub fn protocol_version(mut self, protocol_version: i32) -> Self {
+        self.protocol_version = Some(protocol_version);
+        self
+    }
+
+    pub fn signature(mut self, sig_data: SignatureData) -> Self {
+        self.sig_data = Some(sig_data);
+        self
+    }
+
+    pub fn handshake(&self, peer: SocketAddr, default_protocol: i32) -> Handshake {
+        let (server_address, server_port) = self
+            .address
+            .clone()
+            .unwrap_or_else(|| (peer.ip().to_string(), peer.port()));
+        Handshake {
+            protocol_version: self.protocol_version.unwrap_or(default_protocol).into(),
+            server_address,
+            server_port,
+            next_state: HandshakeState::Login,
+        }
+    }
+
+    pub fn login_start(&self) -> LoginStart {
+        LoginStart {
+            name: self.username.clone(),
+            sig_data: self.sig_data.clone(),
+            player_uuid: self.uuid,
+        }
+    }
+}
+
+/// How to answer resource pack prompts.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ResourcePackPolicy {
+    /// Leave it to the caller.
+    #[default]
+    Ignore,
+    Decline,
+    /// Report the pack as accepted and loaded without downloading it.
+    Accept,
+}
+
+/// Protocol chores `MooshroomConnection` can answer itself while reading
+/// play packets. Packets are still returned to the caller either way.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Housekeeping {
+    pub keep_alive: bool,
+    pub confirm_teleport: bool,
+    pub pong: bool,
+    pub resource_pack: ResourcePackPolicy,
+    /// Send our brand, plugin channels and client information on joining.
+    pub announce: bool,
+    /// Send the replies of plugin channel handlers.
+    pub plugin_channels: bool,
+    /// Acknowledge chat messages once enough are unacknowledged.
+    pub chat_acks: bool,
+}
+
+impl Housekeeping {
+    pub fn enabled() -> Self {
+        Self {
+            keep_alive: true,
+            confirm_teleport: true,
+            pong: true,
+            resource_pack: ResourcePackPolicy::Accept,
+            announce: true,
+            plugin_channels: true,
+            chat_acks: true,
+        }
+    }
+}
+
 pub struct MooshroomConnection {
     sock: MooshroomProto<TcpStream>,
     stage: Stage,
+    options: LoginOptions,
+    plugins: LoginPlugins,
+    housekeeping: Housekeeping,
+    movement: MovementTracker,
+    chat: ChatSession,
+    plugin_channels: PluginChannels,
+    client_information: ClientInformation,
+    /// The last block change sequence number sent, and the last one the
+    /// server acknowledged.
+    sequence: i32,
+    acknowledged_sequence: i32,
 }
 
 impl MooshroomConnection {
     pub fn new(sock: TcpStream) -> Self {
+        Self::with_options(sock, LoginOptions::default())
+    }
+
+    pub fn with_options(sock: TcpStream, options: LoginOptions) -> Self {
         Self {
             sock: MooshroomProto::new(sock),
             stage: Stage::Handshake,
+            options,
+            plugins: LoginPlugins::default(),
+            housekeeping: Housekeeping::default(),
+            movement: MovementTracker::default(),
+            chat: ChatSession::default(),
+            plugin_channels: PluginChannels::default(),
+            client_information: ClientInformation::default(),
+            sequence: 0,
+            acknowledged_sequence: 0,
         }
     }
 
@@ -38,47 +216,455 @@ impl MooshroomConnection {
         &self.stage
     }
 
+    pub fn options(&self) -> &LoginOptions {
+        &self.options
+    }
+
+    pub fn set_housekeeping(&mut self, housekeeping: Housekeeping) {
+        self.housekeeping = housekeeping;
+    }
+
+    /// Adds an interceptor that sees every packet sent and received.
+    pub fn add_interceptor(&mut self, interceptor: impl Interceptor + 'static) {
+        self.sock.add_interceptor(interceptor);
+    }
+
+    /// Handlers for plugin messages in the play state, and the channels the
+    /// server registered.
+    pub fn plugin_channels(&mut self) -> &mut PluginChannels {
+        &mut self.plugin_channels
+    }
+
+    pub fn client_information(&self) -> &ClientInformation {
+        &self.client_information
+    }
+
+    /// Changes the client information, sending it right away when already
+    /// playing.
+    pub fn set_client_information(&mut self, information: ClientInformation) -> Result<()> {
+        self.client_information = information;
+        if matches!(self.stage, Stage::Play(_)) {
+            self.sock.write_packet(&self.client_information)?;
+        }
+        Ok(())
+    }
+
+    /// Sends our brand, plugin channels and client information, as the
+    /// vanilla client does after joining.
+    pub fn announce(&mut self) -> Result<()> {
+        for message in self.plugin_channels.announce()? {
+            self.sock.write_packet(&message)?;
+        }
+        self.sock.write_packet(&self.client_information)
+    }
+
+    /// Handlers for login plugin requests received during the next login.
+    pub fn login_plugins(&mut self) -> &mut LoginPlugins {
+        &mut self.plugins
+    }
+
     pub fn handshake_offline(&mut self) -> Result<()> {
-        let ep = self.sock.inner.peer_addr()?;
+        let start = self.options.login_start();
+        self.login(start, |_, _| {
+            Err(MooshroomError::AuthenticationFailed(
+                "server is in online mode".into(),
+            ))
+        })
+    }
 
-        self.sock.write_packet(&Handshake {
-            server_address: ep.ip().to_string(),
-            server_port: ep.port(),
-            protocol_version: self.sock.protocal_version().into(),
-            next_state: HandshakeState::Login,
-        })?;
+    /// Logs in to an online-mode server as `profile`, joining the session
+    /// through `authenticator` and enabling encryption. The username and UUID
+    /// in the options are replaced by the profile's.
+    pub fn handshake_online(
+        &mut self,
+        profile: &GameProfile,
+        authenticator: &impl SessionAuthenticator,
+    ) -> Result<()> {
+        let start = LoginStart {
+            name: profile.name.clone(),
+            player_uuid: Some(profile.uuid),
+            ..self.options.login_start()
+        };
+        self.login(start, |sock, request| {
+            auth::respond_to_encryption_request(sock, &request, profile, authenticator)
+        })
+    }
 
-        self.sock.write_packet(&LoginStart {
-            name: "mooshroom".into(),
-            player_uuid: None,
-            sig_data: None,
-        })?;
+    fn login(
+        &mut self,
+        start: LoginStart,
+        mut on_encryption: impl FnMut(&mut MooshroomProto<TcpStream>, EncryptionRequest) -> Result<()>,
+    ) -> Result<()> {
+        let ep = self.sock.inner.peer_addr()?;
+        let handshake = self.options.handshake(ep, self.sock.protocal_version());
+
+        self.sock.write_packet(&handshake)?;
+        self.stage = Stage::Login;
+        self.sock.write_packet(&start)?;
 
         loop {
             let resp: LoginStage = self.sock.read_one_of()?;
 
             match resp {
+                LoginStage::EncryptionRequest(r) => on_encryption(&mut self.sock, r)?,
+                LoginStage::LoginPluginRequest(r) => {
+                    let response = self.plugins.respond(&r)?;
+                    self.sock.write_packet(&response)?;
+                }
                 LoginStage::SetCompression(n) => self.sock.codec.set_compression(n.threshold.0),
                 LoginStage::Success(LoginSuccess { uuid, .. }) => {
                     self.stage = Stage::Play(uuid);
                     break;
                 }
-                _ => {}
+                LoginStage::Disconnect(Disconnect(reason)) => {
+                    return Err(MooshroomError::Disconnected { reason })
+                }
             }
         }
 
         Ok(())
     }
 
+    /// Returns [`MooshroomError::Disconnected`] when the server kicks the
+    /// client.
     pub fn next_play_packet(&mut self) -> Result<PlayStage> {
-        self.sock.read_one_of()
+        let packet = self.sock.read_one_of()?;
+        self.housekeep(&packet)?;
+        Ok(packet)
+    }
+
+    pub fn try_next_play_packet(&mut self) -> Result<Option<PlayStage>> {
+        let packet = self.sock.try_read_one_of()?;
+        if let Some(packet) = &packet {
+            self.housekeep(packet)?;
+        }
+        Ok(packet)
+    }
+
+    fn housekeep(&mut self, packet: &PlayStage) -> Result<()> {
+        let h = self.housekeeping;
+        if let PlayStage::SynchronizePlayerPosition(p) = packet {
+            self.movement.apply_teleport(p);
+        }
+        if let PlayStage::AcknowledgeBlockChange(p) = packet {
+            self.acknowledged_sequence = self.acknowledged_sequence.max(p.sequence_id.0);
+        }
+        if let PlayStage::PluginMessage(p) = packet {
+            match self.plugin_channels.handle(p) {
+                Some(reply) if h.plugin_channels => self.sock.write_packet(&reply)?,
+                _ => {}
+            }
+        }
+        if let PlayStage::PlayerChatMessage(p) = packet {
+            match self.chat.observe(p) {
+                Some(ack) if h.chat_acks => self.sock.write_packet(&ack)?,
+                _ => {}
+            }
+        }
+        match packet {
+            PlayStage::Disconnect(p) => Err(MooshroomError::Disconnected {
+                reason: p.0.clone(),
+            }),
+            PlayStage::KeepAlive(p) if h.keep_alive => self.respond_to_keep_alive(p.0),
+            PlayStage::SynchronizePlayerPosition(p) if h.confirm_teleport => {
+                self.sock.write_packet(&ConfirmTeleportation {
+                    teleport_id: p.teleport_id,
+                })
+            }
+            PlayStage::Ping(p) if h.pong => self.sock.write_packet(&Pong(p.0)),
+            PlayStage::Login(_) if h.announce => self.announce(),
+            PlayStage::ResourcePack(_) => match h.resource_pack {
+                ResourcePackPolicy::Ignore => Ok(()),
+                ResourcePackPolicy::Decline => {
+                    self.respond_to_resource_pack(ResourcePackResult::Declined)
+                }
+                ResourcePackPolicy::Accept => {
+                    self.respond_to_resource_pack(ResourcePackResult::Accepted)?;
+                    self.respond_to_resource_pack(ResourcePackResult::SuccessfullyLoaded)
+                }
+            },
+            _ => Ok(()),
+        }
+    }
+
+    pub fn set_read_timeout(&self, timeout: Option<Duration>) -> Result<()> {
+        self.sock.set_read_timeout(timeout)
+    }
+
+    pub fn set_write_timeout(&self, timeout: Option<Duration>) -> Result<()> {
+        self.sock.set_write_timeout(timeout)
+    }
+
+    pub fn set_nonblocking(&mut self, nonblocking: bool) -> Result<()> {
+        self.sock.set_nonblocking(nonblocking)
+    }
+
+    /// The player's position, kept in sync with server teleports. Move the
+    /// player by changing it and calling [`Self::tick_movement`].
+    pub fn movement(&mut self) -> &mut MovementTracker {
+        &mut self.movement
+    }
+
+    /// Sends whichever movement packet is needed this tick. Call once every
+    /// 50ms while in play.
+    pub fn tick_movement(&mut self) -> Result<()> {
+        match self.movement.tick() {
+            Some(MovementPacket::Position(p)) => self.send(&p),
+            Some(MovementPacket::PositionAndRotation(p)) => self.send(&p),
+            Some(MovementPacket::Rotation(p)) => self.send(&p),
+            Some(MovementPacket::OnGround(p)) => self.send(&p),
+            None => Ok(()),
+        }
+    }
+
+    /// Chat state, set a signer here to send signed messages.
+    pub fn chat(&mut self) -> &mut ChatSession {
+        &mut self.chat
+    }
+
+    pub fn send_chat(&mut self, message: &str) -> Result<()> {
+        let uuid = self.player_uuid()?;
+        let p = self.chat.chat_message(uuid, message)?;
+        self.sock.write_packet(&p)
+    }
+
+    /// Sends `command`, without the leading slash. See
+    /// [`ChatSession::chat_command`] for `arguments`.
+    pub fn send_command(&mut self, command: &str, arguments: &[(&str, &str)]) -> Result<()> {
+        let uuid = self.player_uuid()?;
+        let p = self.chat.chat_command(uuid, command, arguments)?;
+        self.sock.write_packet(&p)
+    }
+
+    fn player_uuid(&self) -> Result<uuid::Uuid> {
+        match self.stage {
+            Stage::Play(uuid) => Ok(uuid),
+            _ => Err(MooshroomError::NotInPlay),
+        }
+    }
+
+    /// How many block changes the server has not acknowledged yet. Until it
+    /// does, the server may still revert them.
+    pub fn pending_block_changes(&self) -> i32 {
+        self.sequence - self.acknowledged_sequence
+    }
+
+    fn next_sequence(&mut self) -> VarInt {
+        self.sequence += 1;
+        VarInt(self.sequence)
+    }
+
+    pub fn swing_arm(&mut self, hand: Hand) -> Result<()> {
+        self.sock.write_packet(&SwingArm { hand })
+    }
+
+    /// Starts digging the block at `location`. This breaks it in creative
+    /// mode or if it breaks instantly, otherwise call
+    /// [`Self::finish_digging`] once the block's break time has passed.
+    pub fn dig(&mut self, location: Position, face: BlockFace) -> Result<()> {
+        self.player_action(PlayerActionStatus::StartedDigging, location, face)?;
+        self.swing_arm(Hand::MainHand)
+    }
+
+    pub fn finish_digging(&mut self, location: Position, face: BlockFace) -> Result<()> {
+        self.player_action(PlayerActionStatus::FinishedDigging, location, face)?;
+        self.swing_arm(Hand::MainHand)
+    }
+
+    pub fn cancel_digging(&mut self, location: Position, face: BlockFace) -> Result<()> {
+        self.player_action(PlayerActionStatus::CancelledDigging, location, face)
+    }
+
+    fn player_action(
+        &mut self,
+        status: PlayerActionStatus,
+        location: Position,
+        face: BlockFace,
+    ) -> Result<()> {
+        let sequence = self.next_sequence();
+        self.sock.write_packet(&PlayerAction {
+            status,
+            location,
+            face,
+            sequence,
+        })
+    }
+
+    /// Uses the held item on a block face, which places blocks and opens
+    /// containers.
+    pub fn use_item_on(&mut self, location: Position, face: BlockFace, hand: Hand) -> Result<()> {
+        let sequence = self.next_sequence();
+        self.sock.write_packet(&UseItemOn {
+            hand,
+            location,
+            face,
+            cursor: face.center(),
+            inside_block: false,
+            sequence,
+        })?;
+        self.swing_arm(hand)
+    }
+
+    pub fn use_item(&mut self, hand: Hand) -> Result<()> {
+        let sequence = self.next_sequence();
+        self.sock.write_packet(&UseItem { hand, sequence })
+    }
+
+    pub fn attack(&mut self, entity_id: VarInt) -> Result<()> {
+        self.sock.write_packet(&Interact {
+            entity_id,
+            action: InteractAction::Attack,
+            sneaking: false,
+        })?;
+        self.swing_arm(Hand::MainHand)
+    }
+
+    pub fn interact(&mut self, entity_id: VarInt, hand: Hand) -> Result<()> {
+        self.sock.write_packet(&Interact {
+            entity_id,
+            action: InteractAction::Interact(hand),
+            sneaking: false,
+        })?;
+        self.swing_arm(hand)
+    }
+
+    pub fn send<P>(&mut self, packet: &P) -> Result<()>
+    where
+        P: MooshroomPacket<DEFAULT_PROTOCAL_VERSION> + Debug + 'static,
+    {
+        self.sock.write_packet(packet)
     }
 
     pub fn respond_to_keep_alive(&mut self, id: i64) -> Result<()> {
         self.sock.write_packet(&KeepAliveResponse(id))
     }
 
+    pub fn respond_to_resource_pack(&mut self, result: ResourcePackResult) -> Result<()> {
+        self.sock.write_packet(&ResourcePackResponse { result })
+    }
+
     pub fn respawn(&mut self) -> Result<()> {
         self.sock.write_packet(&player::Action::Respawn)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::net::TcpListener;
+
+    use super::*;
+
+    #[test]
+    fn test_login_options() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let uuid = uuid::Uuid::new_v4();
+
+        let server = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut proto = MooshroomProto::new(stream);
+            let handshake: Handshake = proto.read_packet().unwrap();
+            let start: LoginStart = proto.read_packet().unwrap();
+            proto
+                .write_packet(&LoginSuccess {
+                    uuid: start.player_uuid.unwrap(),
+                    username: start.name.clone(),
+                    properties: Vec::new(),
+                })
+                .unwrap();
+            (handshake, start)
+        });
+
+        let options = LoginOptions::new("Steve")
+            .uuid(uuid)
+            .address("play.example.com", 25565)
+            .protocol_version(759);
+        let mut conn =
+            MooshroomConnection::with_options(TcpStream::connect(addr).unwrap(), options);
+        conn.handshake_offline().unwrap();
+        assert!(matches!(conn.stage(), Stage::Play(u) if *u == uuid));
+
+        let (handshake, start) = server.join().unwrap();
+        assert_eq!(handshake.server_address, "play.example.com");
+        assert_eq!(handshake.server_port, 25565);
+        assert_eq!(handshake.protocol_version, 759);
+        assert_eq!(start.name, "Steve");
+        assert_eq!(start.player_uuid, Some(uuid));
+    }
+
+    #[test]
+    fn test_housekeeping() {
+        use crate::server::play::{
+            metadata::{KeepAlive, Ping, ResourcePack},
+            player::SynchronizePlayerPosition,
+        };
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let stream = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let mut conn = MooshroomConnection::new(stream);
+        conn.set_housekeeping(Housekeeping::enabled());
+        let mut server = MooshroomProto::new(listener.accept().unwrap().0);
+
+        server.write_packet(&KeepAlive(5)).unwrap();
+        server
+            .write_packet(&SynchronizePlayerPosition {
+                teleport_id: 9.into(),
+                ..Default::default()
+            })
+            .unwrap();
+        server.write_packet(&Ping(3)).unwrap();
+        server.write_packet(&ResourcePack::default()).unwrap();
+        for _ in 0..4 {
+            conn.next_play_packet().unwrap();
+        }
+
+        assert_eq!(server.read_packet::<KeepAliveResponse>().unwrap().0, 5);
+        assert_eq!(
+            server
+                .read_packet::<ConfirmTeleportation>()
+                .unwrap()
+                .teleport_id,
+            9
+        );
+        assert_eq!(server.read_packet::<Pong>().unwrap().0, 3);
+        for expected in [
+            ResourcePackResult::Accepted,
+            ResourcePackResult::SuccessfullyLoaded,
+        ] {
+            assert_eq!(
+                server.read_packet::<ResourcePackResponse>().unwrap().result,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn test_block_change_sequence() {
+        use crate::server::play::world::AcknowledgeBlockChange;
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let stream = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let mut conn = MooshroomConnection::new(stream);
+        let mut server = MooshroomProto::new(listener.accept().unwrap().0);
+
+        let pos = Position { x: 1, z: -2, y: 64 };
+        conn.dig(pos, BlockFace::Top).unwrap();
+        conn.use_item_on(pos, BlockFace::Top, Hand::MainHand)
+            .unwrap();
+        assert_eq!(conn.pending_block_changes(), 2);
+
+        let dig: PlayerAction = server.read_packet().unwrap();
+        assert_eq!((dig.location, dig.sequence), (pos, VarInt(1)));
+        server.read_packet::<SwingArm>().unwrap();
+        let place: UseItemOn = server.read_packet().unwrap();
+        assert_eq!(place.sequence, VarInt(2));
+
+        server
+            .write_packet(&AcknowledgeBlockChange {
+                sequence_id: VarInt(2),
+            })
+            .unwrap();
+        conn.next_play_packet().unwrap();
+        assert_eq!(conn.pending_block_changes(), 0);
+    }
+}
@@ -0,0 +1,259 @@
+use std::fmt::{Debug, Write as _};
+
+use mooshroom_core::data::MooshroomCollection;
+
+use super::{codec::RawPacket, recording::ProtocolState};
+use crate::{
+    client::{
+        self,
+        handshake::{Handshake, HandshakeState},
+    },
+    core::{
+        error::{MooshroomError, Result},
+        io::{MooshroomPacket, DEFAULT_PROTOCAL_VERSION},
+    },
+    server::{
+        self,
+        login::{EncryptionRequest, LoginSuccess, SetCompression},
+    },
+};
+
+const PV: usize = DEFAULT_PROTOCAL_VERSION;
+
+/// Which side a packet is sent to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bound {
+    Serverbound,
+    Clientbound,
+}
+
+impl Bound {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Serverbound => "serverbound",
+            Self::Clientbound => "clientbound",
+        }
+    }
+
+    pub fn arrow(self) -> &'static str {
+        match self {
+            Self::Serverbound => "C->S",
+            Self::Clientbound => "S->C",
+        }
+    }
+}
+
+/// What could be made of a packet.
+#[derive(Debug, Default)]
+pub struct Decoded {
+    /// The packet's type, if it is known and parsed.
+    pub name: Option<String>,
+    pub detail: Option<String>,
+    /// Bytes left over after parsing, which usually means the packet's
+    /// layout is wrong or unfinished.
+    pub unread: usize,
+    /// No packet in the collection has this id.
+    pub unknown: bool,
+    /// Why the packet could not be read.
+    pub error: Option<String>,
+}
+
+impl Decoded {
+    fn failed(error: String) -> Self {
+        Self {
+            error: Some(error),
+            ..Default::default()
+        }
+    }
+}
+
+/// Decodes a packet with the collection for its state and direction.
+pub fn decode(state: ProtocolState, bound: Bound, raw: &RawPacket) -> Decoded {
+    use Bound::*;
+    use ProtocolState::*;
+    match (state, bound) {
+        (Handshake, Serverbound) => packet::<client::handshake::Handshake>(raw),
+        (Handshake, Clientbound) => Decoded {
+            unknown: true,
+            ..Default::default()
+        },
+        (Status, Serverbound) => collection::<client::StatusStage>(raw),
+        (Status, Clientbound) => collection::<server::status::StatusStage>(raw),
+        (Login, Serverbound) => collection::<client::LoginStage>(raw),
+        (Login, Clientbound) => collection::<server::login::LoginStage>(raw),
+        (Play, Serverbound) => collection::<client::PlayStage>(raw),
+        (Play, Clientbound) => collection::<server::play::PlayStage>(raw),
+    }
+}
+
+/// Formats a packet body as lowercase hex, for printing packets that could
+/// not be decoded.
+pub fn hex(bytes: &[u8]) -> String {
+    bytes.iter().fold(String::new(), |mut s, b| {
+        let _ = write!(s, "{b:02x}");
+        s
+    })
+}
+
+fn is<P: MooshroomPacket<PV>>(raw: &RawPacket) -> bool {
+    raw.id == P::PACKET_ID
+}
+
+fn packet<P: MooshroomPacket<PV> + Debug>(raw: &RawPacket) -> Decoded {
+    if !is::<P>(raw) {
+        return Decoded {
+            unknown: true,
+            ..Default::default()
+        };
+    }
+    let mut body = &raw.body[..];
+    match P::read(&mut body) {
+        Ok(p) => {
+            let detail = format!("{p:?}");
+            Decoded {
+                name: detail.split([' ', '(']).next().map(str::to_string),
+                detail: Some(detail),
+                unread: body.len(),
+                ..Default::default()
+            }
+        }
+        Err(e) => Decoded::failed(e.to_string()),
+    }
+}
+
+fn collection<C: MooshroomCollection<PV> + Debug>(raw: &RawPacket) -> Decoded {
+    let mut body = &raw.body[..];
+    match C::read_one_of(raw.id, &mut body) {
+        Ok(p) => {
+            // Debug prints the variant wrapping the packet, `Name(Packet { .. })`.
+            let debug = format!("{p:?}");
+            let (name, detail) = match debug.split_once('(') {
+                Some((name, rest)) => (name, rest.strip_suffix(')').unwrap_or(rest)),
+                None => (debug.as_str(), ""),
+            };
+            Decoded {
+                name: Some(name.to_string()),
+                detail: Some(detail.to_string()),
+                unread: body.len(),
+                ..Default::default()
+            }
+        }
+        Err(MooshroomError::NotInCollection(_)) => Decoded {
+            unknown: true,
+            ..Default::default()
+        },
+        Err(e) => Decoded::failed(e.to_string()),
+    }
+}
+
+/// Follows the state of a connection from the packets sent each way, for
+/// tools that only see it go by.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StateTracker {
+    state: ProtocolState,
+    encryption_requested: bool,
+}
+
+impl StateTracker {
+    pub fn new(state: ProtocolState) -> Self {
+        Self {
+            state,
+            encryption_requested: false,
+        }
+    }
+
+    pub fn state(&self) -> ProtocolState {
+        self.state
+    }
+
+    /// Whether the server asked to encrypt the connection, after which only
+    /// the two ends can read it.
+    pub fn encryption_requested(&self) -> bool {
+        self.encryption_requested
+    }
+
+    /// Updates the state from a packet, returning the new compression
+    /// threshold if the packet sets one. Packets sent after it are framed
+    /// with that threshold, both ways.
+    pub fn observe(&mut self, bound: Bound, raw: &RawPacket) -> Result<Option<i32>> {
+        match (bound, self.state) {
+            (Bound::Serverbound, ProtocolState::Handshake) if is::<Handshake>(raw) => {
+                self.state = match raw.decode::<PV, Handshake>()?.next_state {
+                    HandshakeState::Status => ProtocolState::Status,
+                    HandshakeState::Login => ProtocolState::Login,
+                };
+            }
+            (Bound::Clientbound, ProtocolState::Login) if is::<SetCompression>(raw) => {
+                return Ok(Some(raw.decode::<PV, SetCompression>()?.threshold.0));
+            }
+            (Bound::Clientbound, ProtocolState::Login) if is::<LoginSuccess>(raw) => {
+                self.state = ProtocolState::Play;
+            }
+            (Bound::Clientbound, ProtocolState::Login) if is::<EncryptionRequest>(raw) => {
+                self.encryption_requested = true;
+            }
+            _ => {}
+        }
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::play::metadata::KeepAlive;
+
+    #[test]
+    fn test_decode_names() {
+        let raw = RawPacket::from_packet::<PV, _>(&KeepAlive(7)).unwrap();
+        let decoded = decode(ProtocolState::Play, Bound::Clientbound, &raw);
+        assert_eq!(decoded.name.as_deref(), Some("KeepAlive"));
+        assert_eq!(decoded.detail.as_deref(), Some("KeepAlive(7)"));
+        assert_eq!(decoded.unread, 0);
+
+        let mut long = raw.clone();
+        long.body.extend_from_slice(&[1, 2]);
+        assert_eq!(
+            decode(ProtocolState::Play, Bound::Clientbound, &long).unread,
+            2
+        );
+
+        let unknown = RawPacket::new(0x7f.into(), &[][..]);
+        let decoded = decode(ProtocolState::Play, Bound::Clientbound, &unknown);
+        assert!(decoded.name.is_none() && decoded.unknown);
+
+        let short = RawPacket::new(raw.id, &[][..]);
+        let decoded = decode(ProtocolState::Play, Bound::Clientbound, &short);
+        assert!(!decoded.unknown && decoded.error.is_some());
+    }
+
+    #[test]
+    fn test_state_tracker() {
+        let mut tracker = StateTracker::default();
+        let handshake = RawPacket::from_packet::<PV, _>(&Handshake {
+            next_state: HandshakeState::Login,
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(
+            tracker.observe(Bound::Clientbound, &handshake).unwrap(),
+            None
+        );
+        assert_eq!(tracker.state(), ProtocolState::Handshake);
+        tracker.observe(Bound::Serverbound, &handshake).unwrap();
+        assert_eq!(tracker.state(), ProtocolState::Login);
+
+        let compression = RawPacket::from_packet::<PV, _>(&SetCompression {
+            threshold: 64.into(),
+        })
+        .unwrap();
+        assert_eq!(
+            tracker.observe(Bound::Clientbound, &compression).unwrap(),
+            Some(64)
+        );
+        let success = RawPacket::from_packet::<PV, _>(&LoginSuccess::default()).unwrap();
+        tracker.observe(Bound::Clientbound, &success).unwrap();
+        assert_eq!(tracker.state(), ProtocolState::Play);
+        assert!(!tracker.encryption_requested());
+    }
+}
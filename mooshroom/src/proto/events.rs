@@ -0,0 +1,187 @@
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+    fmt::Debug,
+};
+
+use super::connection::MooshroomConnection;
+use crate::{
+    core::{
+        data::MooshroomCollection,
+        error::Result,
+        io::{MooshroomPacket, DEFAULT_PROTOCAL_VERSION},
+    },
+    server::play::PlayStage,
+};
+
+type Handler<S> = Box<dyn FnMut(&mut Context<S>, &dyn Any) -> Result<()>>;
+type AnyHandler<S> = Box<dyn FnMut(&mut Context<S>, &PlayStage) -> Result<()>>;
+
+/// What a handler gets besides the packet.
+pub struct Context<'a, S> {
+    conn: &'a mut MooshroomConnection,
+    pub state: &'a mut S,
+    stopped: &'a mut bool,
+}
+
+impl<'a, S> Context<'a, S> {
+    pub fn send<P>(&mut self, packet: &P) -> Result<()>
+    where
+        P: MooshroomPacket<DEFAULT_PROTOCAL_VERSION> + Debug + 'static,
+    {
+        self.conn.send(packet)
+    }
+
+    pub fn connection(&mut self) -> &mut MooshroomConnection {
+        self.conn
+    }
+
+    /// Makes [`MooshroomClient::run`] return once the current packet has
+    /// been handled.
+    pub fn stop(&mut self) {
+        *self.stopped = true;
+    }
+}
+
+/// Runs a play connection by dispatching each packet to the handlers
+/// registered for its type, instead of matching on [`PlayStage`].
+pub struct MooshroomClient<S = ()> {
+    conn: MooshroomConnection,
+    state: S,
+    handlers: HashMap<TypeId, Vec<Handler<S>>>,
+    any_handlers: Vec<AnyHandler<S>>,
+    stopped: bool,
+}
+
+impl<S> MooshroomClient<S> {
+    /// `conn` should already be in the play state.
+    pub fn new(conn: MooshroomConnection, state: S) -> Self {
+        Self {
+            conn,
+            state,
+            handlers: HashMap::new(),
+            any_handlers: Vec::new(),
+            stopped: false,
+        }
+    }
+
+    /// Registers a handler for packets of type `P`. Handlers for the same
+    /// type run in the order they were added.
+    pub fn on<P: Any>(
+        &mut self,
+        mut handler: impl FnMut(&mut Context<S>, &P) -> Result<()> + 'static,
+    ) -> &mut Self {
+        self.handlers
+            .entry(TypeId::of::<P>())
+            .or_default()
+            .push(Box::new(move |ctx, packet| match packet.downcast_ref() {
+                Some(p) => handler(ctx, p),
+                None => Ok(()),
+            }));
+        self
+    }
+
+    /// Registers a handler that sees every packet, after the typed handlers.
+    pub fn on_any(
+        &mut self,
+        handler: impl FnMut(&mut Context<S>, &PlayStage) -> Result<()> + 'static,
+    ) -> &mut Self {
+        self.any_handlers.push(Box::new(handler));
+        self
+    }
+
+    pub fn state(&self) -> &S {
+        &self.state
+    }
+
+    pub fn state_mut(&mut self) -> &mut S {
+        &mut self.state
+    }
+
+    pub fn connection(&mut self) -> &mut MooshroomConnection {
+        &mut self.conn
+    }
+
+    pub fn into_inner(self) -> (MooshroomConnection, S) {
+        (self.conn, self.state)
+    }
+
+    pub fn dispatch(&mut self, packet: &PlayStage) -> Result<()> {
+        let mut ctx = Context {
+            conn: &mut self.conn,
+            state: &mut self.state,
+            stopped: &mut self.stopped,
+        };
+        let inner = MooshroomCollection::<DEFAULT_PROTOCAL_VERSION>::variant_any(packet);
+        if let Some(handlers) = self.handlers.get_mut(&inner.type_id()) {
+            for handler in handlers {
+                handler(&mut ctx, inner)?;
+            }
+        }
+        for handler in &mut self.any_handlers {
+            handler(&mut ctx, packet)?;
+        }
+        Ok(())
+    }
+
+    /// Reads and dispatches packets until a handler calls
+    /// [`Context::stop`] or an error occurs.
+    pub fn run(&mut self) -> Result<()> {
+        self.stopped = false;
+        while !self.stopped {
+            let packet = self.conn.next_play_packet()?;
+            self.dispatch(&packet)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{TcpListener, TcpStream};
+
+    use super::*;
+    use crate::{
+        client::metadata::KeepAliveResponse,
+        proto::MooshroomProto,
+        server::play::{metadata::KeepAlive, player::SetHealth},
+    };
+
+    #[derive(Default)]
+    struct State {
+        health: f32,
+        packets: usize,
+    }
+
+    #[test]
+    fn test_dispatch_by_type() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let stream = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let mut server = MooshroomProto::new(listener.accept().unwrap().0);
+        server.write_packet(&KeepAlive(4)).unwrap();
+        server
+            .write_packet(&SetHealth {
+                health: 0.,
+                ..Default::default()
+            })
+            .unwrap();
+
+        let mut client = MooshroomClient::new(MooshroomConnection::new(stream), State::default());
+        client
+            .on::<KeepAlive>(|ctx, p| ctx.send(&KeepAliveResponse(p.0)))
+            .on::<SetHealth>(|ctx, p| {
+                ctx.state.health = p.health;
+                ctx.stop();
+                Ok(())
+            })
+            .on_any(|ctx, _| {
+                ctx.state.packets += 1;
+                Ok(())
+            });
+        client.run().unwrap();
+
+        assert_eq!(client.state().health, 0.);
+        assert_eq!(client.state().packets, 2);
+        assert_eq!(server.read_packet::<KeepAliveResponse>().unwrap().0, 4);
+    }
+}
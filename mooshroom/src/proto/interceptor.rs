@@ -0,0 +1,171 @@
+use std::{any::Any, collections::VecDeque, fmt::Debug};
+
+use super::codec::RawPacket;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Direction {
+    Incoming,
+    Outgoing,
+}
+
+#[derive(Debug, Clone)]
+pub enum Intercept {
+    Forward,
+    Drop,
+    Replace(RawPacket),
+}
+
+/// Packets an interceptor wants to add to the stream. Injected packets are
+/// not passed through the interceptors again.
+#[derive(Debug, Default)]
+pub struct InterceptContext {
+    pub(crate) incoming: VecDeque<RawPacket>,
+    pub(crate) outgoing: VecDeque<RawPacket>,
+}
+
+impl InterceptContext {
+    pub fn inject_incoming(&mut self, packet: RawPacket) {
+        self.incoming.push_back(packet);
+    }
+
+    pub fn inject_outgoing(&mut self, packet: RawPacket) {
+        self.outgoing.push_back(packet);
+    }
+}
+
+/// A decoded packet or collection, as seen by [`Interceptor::on_decoded`].
+pub struct Decoded<'a> {
+    type_name: &'static str,
+    value: &'a dyn Any,
+    debug: &'a dyn Debug,
+}
+
+impl<'a> Decoded<'a> {
+    pub(crate) fn new<T: Any + Debug>(value: &'a T) -> Self {
+        Self {
+            type_name: std::any::type_name::<T>(),
+            value,
+            debug: value,
+        }
+    }
+
+    pub fn type_name(&self) -> &'static str {
+        self.type_name
+    }
+
+    pub fn downcast_ref<T: Any>(&self) -> Option<&'a T> {
+        self.value.downcast_ref()
+    }
+}
+
+impl<'a> Debug for Decoded<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.debug.fmt(f)
+    }
+}
+
+/// Hooks run by `MooshroomProto` for every packet it reads or writes.
+pub trait Interceptor: Send {
+    /// Called with the framed packet before it is decoded (incoming) or after
+    /// it is encoded (outgoing).
+    fn on_raw(
+        &mut self,
+        _direction: Direction,
+        _packet: &RawPacket,
+        _ctx: &mut InterceptContext,
+    ) -> Intercept {
+        Intercept::Forward
+    }
+
+    /// Called with the typed value when the packet was read or written as a
+    /// typed packet rather than as a [`RawPacket`].
+    fn on_decoded(&mut self, _direction: Direction, _packet: &Decoded) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::{
+        client::metadata::KeepAliveResponse,
+        core::io::DEFAULT_PROTOCAL_VERSION,
+        proto::{codec::MooshroomCodec, testing::MockStream, MooshroomProto},
+        server::play::metadata::KeepAlive,
+    };
+
+    const PV: usize = DEFAULT_PROTOCAL_VERSION;
+
+    #[derive(Default)]
+    struct FaultInjector {
+        seen: Arc<Mutex<Vec<(Direction, &'static str)>>>,
+    }
+
+    impl Interceptor for FaultInjector {
+        fn on_raw(
+            &mut self,
+            direction: Direction,
+            packet: &RawPacket,
+            ctx: &mut InterceptContext,
+        ) -> Intercept {
+            match direction {
+                Direction::Incoming => {
+                    let p: KeepAlive = packet.decode::<PV, _>().unwrap();
+                    if p.0 == 1 {
+                        ctx.inject_outgoing(
+                            RawPacket::from_packet::<PV, _>(&KeepAliveResponse(1)).unwrap(),
+                        );
+                        Intercept::Drop
+                    } else {
+                        Intercept::Forward
+                    }
+                }
+                Direction::Outgoing => Intercept::Replace(
+                    RawPacket::from_packet::<PV, _>(&KeepAliveResponse(99)).unwrap(),
+                ),
+            }
+        }
+
+        fn on_decoded(&mut self, direction: Direction, packet: &Decoded) {
+            self.seen
+                .lock()
+                .unwrap()
+                .push((direction, packet.type_name()));
+        }
+    }
+
+    #[test]
+    fn test_interceptor_drop_inject_replace() {
+        let mut server = MooshroomCodec::<PV>::new();
+        let mut rx = server.encode(&KeepAlive(1)).unwrap();
+        rx.extend(server.encode(&KeepAlive(2)).unwrap());
+
+        let injector = FaultInjector::default();
+        let seen = injector.seen.clone();
+        let mut proto = MooshroomProto::new(MockStream::new(rx));
+        proto.add_interceptor(injector);
+
+        let p: KeepAlive = proto.read_packet().unwrap();
+        assert_eq!(p.0, 2);
+        // The injected reply goes out without waiting for a write.
+        let mut client = MooshroomCodec::<PV>::new();
+        client.add_bytes(&std::mem::take(&mut proto.inner.tx));
+        let injected: KeepAliveResponse = client.read_packet().unwrap().unwrap();
+        assert_eq!(injected.0, 1);
+
+        proto.write_packet(&KeepAliveResponse(2)).unwrap();
+        client.add_bytes(&proto.inner.tx);
+        let replaced: KeepAliveResponse = client.read_packet().unwrap().unwrap();
+        assert_eq!(replaced.0, 99);
+        assert_eq!(
+            *seen.lock().unwrap(),
+            vec![
+                (Direction::Incoming, std::any::type_name::<KeepAlive>()),
+                (
+                    Direction::Outgoing,
+                    std::any::type_name::<KeepAliveResponse>()
+                ),
+            ]
+        );
+    }
+}
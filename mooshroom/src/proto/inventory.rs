@@ -0,0 +1,293 @@
+use mooshroom_core::varint::VarInt;
+
+use crate::{
+    client::inventory::{ChangedSlot, ClickContainer, ClickMode, OUTSIDE_WINDOW},
+    server::play::crafting::{SetContainerContent, SetContainerSlot, Slot, SlotData},
+};
+
+/// The hotbar button that swaps with the offhand.
+pub const OFFHAND_BUTTON: i8 = 40;
+
+/// A local copy of an open window, used to predict what a click changes so
+/// the server accepts it without resending the whole window.
+#[derive(Debug, Clone)]
+pub struct Window {
+    pub window_id: u8,
+    pub state_id: VarInt,
+    pub slots: Vec<Slot>,
+    pub carried: Slot,
+    /// Looks up the stack size of an item id. Defaults to 64 for everything,
+    /// set it if tools or other unstackable items are involved.
+    pub max_stack_size: fn(VarInt) -> u8,
+}
+
+impl Default for Window {
+    fn default() -> Self {
+        Self {
+            window_id: 0,
+            state_id: VarInt(0),
+            slots: Vec::new(),
+            carried: None,
+            max_stack_size: |_| 64,
+        }
+    }
+}
+
+impl From<&SetContainerContent> for Window {
+    fn from(p: &SetContainerContent) -> Self {
+        let mut w = Self::default();
+        w.apply_content(p);
+        w
+    }
+}
+
+impl Window {
+    pub fn apply_content(&mut self, p: &SetContainerContent) {
+        self.window_id = p.window_id;
+        self.state_id = p.state_id;
+        self.slots = p.slot_data.clone();
+        self.carried = p.carried_item.clone();
+    }
+
+    /// Applies a slot update, ignoring it if it is for another window.
+    pub fn apply_slot(&mut self, p: &SetContainerSlot) {
+        if p.window_id == -1 {
+            self.carried = p.slot_data.clone();
+            return;
+        }
+        if p.window_id as u8 != self.window_id {
+            return;
+        }
+        self.state_id = p.state_id;
+        if let Some(slot) = self.slots.get_mut(p.slot as usize) {
+            *slot = p.slot_data.clone();
+        }
+    }
+
+    /// Applies a click locally and returns the packet describing it.
+    ///
+    /// `QuickMove` and `QuickCraft` depend on the window layout and are not
+    /// predicted; they are sent with no changed slots and the server replies
+    /// with the real contents.
+    pub fn click(&mut self, slot: i16, button: i8, mode: ClickMode) -> ClickContainer {
+        let before = self.slots.clone();
+        match mode {
+            ClickMode::Pickup => self.pickup(slot, button),
+            ClickMode::Swap => self.swap(slot, button),
+            ClickMode::Clone => self.clone_stack(slot),
+            ClickMode::Throw => self.throw(slot, button),
+            ClickMode::PickupAll => self.pickup_all(),
+            ClickMode::QuickMove | ClickMode::QuickCraft => {}
+        }
+
+        let changed_slots = before
+            .into_iter()
+            .zip(&self.slots)
+            .enumerate()
+            .filter(|(_, (old, new))| old != *new)
+            .map(|(i, (_, new))| ChangedSlot {
+                slot: i as i16,
+                item: new.clone(),
+            })
+            .collect();
+        ClickContainer {
+            window_id: self.window_id,
+            state_id: self.state_id,
+            slot,
+            button,
+            mode,
+            changed_slots,
+            carried_item: self.carried.clone(),
+        }
+    }
+
+    fn max_stack(&self, item: &SlotData) -> u8 {
+        (self.max_stack_size)(item.item_id)
+    }
+
+    fn slot_mut(&mut self, slot: i16) -> Option<&mut Slot> {
+        usize::try_from(slot)
+            .ok()
+            .and_then(|i| self.slots.get_mut(i))
+    }
+
+    fn pickup(&mut self, slot: i16, button: i8) {
+        if slot == OUTSIDE_WINDOW {
+            // Left click drops the whole carried stack, right click drops one.
+            if button == 0 {
+                self.carried = None;
+            } else {
+                take(&mut self.carried, 1);
+            }
+            return;
+        }
+        let Some(target) = self.slots.get(slot as usize).cloned() else {
+            return;
+        };
+        let carried = self.carried.take();
+        let (new_slot, new_carried) = match (target, carried) {
+            (None, None) => (None, None),
+            (Some(s), None) => {
+                let taken = if button == 0 {
+                    s.item_count
+                } else {
+                    s.item_count.div_ceil(2)
+                };
+                let mut left = Some(s);
+                let picked = take(&mut left, taken);
+                (left, picked)
+            }
+            (None, Some(c)) => {
+                let count = if button == 0 { c.item_count } else { 1 };
+                let count = count.min(self.max_stack(&c));
+                let mut carried = Some(c);
+                let placed = take(&mut carried, count);
+                (placed, carried)
+            }
+            (Some(mut s), Some(mut c)) if stacks_with(&s, &c) => {
+                let space = self.max_stack(&s).saturating_sub(s.item_count);
+                let count = if button == 0 { c.item_count } else { 1 }.min(space);
+                s.item_count += count;
+                c.item_count -= count;
+                (Some(s), Some(c).filter(|c| c.item_count > 0))
+            }
+            (Some(s), Some(c)) => (Some(c), Some(s)),
+        };
+        if let Some(target) = self.slot_mut(slot) {
+            *target = new_slot;
+        }
+        self.carried = new_carried;
+    }
+
+    fn swap(&mut self, slot: i16, button: i8) {
+        let hotbar = match (self.window_id, button) {
+            // The offhand is only part of the player inventory window.
+            (0, OFFHAND_BUTTON) => 45,
+            (_, 0..=8) if self.window_id == 0 => 36 + button as usize,
+            (_, 0..=8) if self.slots.len() >= 9 => self.slots.len() - 9 + button as usize,
+            _ => return,
+        };
+        if slot < 0 || slot as usize >= self.slots.len() || hotbar >= self.slots.len() {
+            return;
+        }
+        self.slots.swap(slot as usize, hotbar);
+    }
+
+    fn clone_stack(&mut self, slot: i16) {
+        if self.carried.is_some() {
+            return;
+        }
+        if let Some(Some(s)) = self.slots.get(slot as usize) {
+            let mut s = s.clone();
+            s.item_count = self.max_stack(&s);
+            self.carried = Some(s);
+        }
+    }
+
+    fn throw(&mut self, slot: i16, button: i8) {
+        if self.carried.is_some() {
+            return;
+        }
+        if let Some(target) = self.slot_mut(slot) {
+            // Button 0 drops one item, button 1 the whole stack.
+            let count = if button == 0 { 1 } else { u8::MAX };
+            take(target, count);
+        }
+    }
+
+    /// Double click: gathers matching items into the carried stack, taking
+    /// from partial stacks before full ones like the vanilla client.
+    fn pickup_all(&mut self) {
+        let Some(mut carried) = self.carried.take() else {
+            return;
+        };
+        let max = self.max_stack(&carried);
+        for full_stacks in [false, true] {
+            for slot in self.slots.iter_mut() {
+                if carried.item_count >= max {
+                    break;
+                }
+                let matches = slot.as_ref().is_some_and(|s| {
+                    stacks_with(s, &carried) && (s.item_count >= max) == full_stacks
+                });
+                if matches {
+                    let taken = take(slot, max - carried.item_count);
+                    carried.item_count += taken.map_or(0, |t| t.item_count);
+                }
+            }
+        }
+        self.carried = Some(carried);
+    }
+}
+
+fn stacks_with(a: &SlotData, b: &SlotData) -> bool {
+    a.item_id == b.item_id && a.nbt == b.nbt
+}
+
+/// Takes up to `count` items out of `slot`, emptying it if none are left.
+fn take(slot: &mut Slot, count: u8) -> Slot {
+    let s = slot.as_mut()?;
+    let count = count.min(s.item_count);
+    let mut taken = s.clone();
+    taken.item_count = count;
+    s.item_count -= count;
+    if s.item_count == 0 {
+        *slot = None;
+    }
+    Some(taken).filter(|t| t.item_count > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stack(item_id: i32, item_count: u8) -> Slot {
+        Some(SlotData {
+            item_id: VarInt(item_id),
+            item_count,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_click_prediction() {
+        let mut w = Window::from(&SetContainerContent {
+            window_id: 0,
+            state_id: VarInt(3),
+            slot_data: vec![None; 46],
+            carried_item: None,
+        });
+        w.slots[9] = stack(1, 10);
+        w.slots[10] = stack(1, 60);
+
+        // Right click picks up half, rounded up.
+        let p = w.click(9, 1, ClickMode::Pickup);
+        assert_eq!(p.state_id, VarInt(3));
+        assert_eq!(p.carried_item, stack(1, 5));
+        assert_eq!(
+            p.changed_slots,
+            vec![ChangedSlot {
+                slot: 9,
+                item: stack(1, 5),
+            }]
+        );
+
+        // Left click onto a matching stack fills it up to 64.
+        let p = w.click(10, 0, ClickMode::Pickup);
+        assert_eq!(p.carried_item, stack(1, 1));
+        assert_eq!(p.changed_slots[0].item, stack(1, 64));
+
+        // Double click empties partial stacks before touching full ones.
+        w.slots[11] = stack(2, 1);
+        let p = w.click(9, 0, ClickMode::PickupAll);
+        assert_eq!(p.carried_item, stack(1, 64));
+        assert_eq!(w.slots[9], None);
+        assert_eq!(w.slots[10], stack(1, 6));
+        assert_eq!(w.slots[11], stack(2, 1));
+
+        w.carried = None;
+        let p = w.click(10, 2, ClickMode::Swap);
+        assert_eq!(p.changed_slots.len(), 2);
+        assert_eq!(w.slots[38], stack(1, 6));
+    }
+}
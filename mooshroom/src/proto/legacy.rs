@@ -0,0 +1,353 @@
+use std::{
+    io::{ErrorKind, Read, Write},
+    net::TcpStream,
+    time::Duration,
+};
+
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+
+use crate::core::error::{MooshroomError, Result};
+
+const PING: u8 = 0xFE;
+const PING_PAYLOAD: u8 = 0x01;
+const PLUGIN_MESSAGE: u8 = 0xFA;
+const KICK: u8 = 0xFF;
+const PING_HOST_CHANNEL: &str = "MC|PingHost";
+
+/// Protocol version sent by 1.6 clients in `MC|PingHost`.
+pub const LEGACY_PROTOCOL_VERSION: u8 = 74;
+
+/// How long the server waits for the rest of a legacy ping after the
+/// leading `0xFE` before assuming a beta client.
+const DETECT_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// The flavours of server list ping used before the netty rewrite (1.7).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LegacyPing {
+    /// Beta 1.8 to 1.3, a lone `0xFE`.
+    Beta,
+    /// 1.4 and 1.5, `0xFE 0x01`.
+    V1_4,
+    /// 1.6, `0xFE 0x01` followed by a `MC|PingHost` plugin message.
+    V1_6 {
+        protocol_version: u8,
+        hostname: String,
+        port: i32,
+    },
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LegacyStatus {
+    /// Not sent to beta clients.
+    pub protocol_version: i32,
+    /// Not sent to beta clients.
+    pub version: String,
+    pub motd: String,
+    pub online_players: i32,
+    pub max_players: i32,
+}
+
+fn write_utf16(writer: &mut impl Write, s: &str) -> Result<()> {
+    let chars: Vec<u16> = s.encode_utf16().collect();
+    writer.write_u16::<BigEndian>(chars.len() as u16)?;
+    for c in chars {
+        writer.write_u16::<BigEndian>(c)?;
+    }
+    Ok(())
+}
+
+fn read_utf16(reader: &mut impl Read) -> Result<String> {
+    let len = reader.read_u16::<BigEndian>()?;
+    let mut chars = Vec::with_capacity(len as usize);
+    for _ in 0..len {
+        chars.push(reader.read_u16::<BigEndian>()?);
+    }
+    String::from_utf16(&chars).map_err(|_| MooshroomError::InvalidLegacyPing("invalid utf-16"))
+}
+
+fn parse_number(s: Option<&str>) -> Result<i32> {
+    s.and_then(|s| s.parse().ok())
+        .ok_or(MooshroomError::InvalidLegacyPing("invalid number"))
+}
+
+impl LegacyPing {
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let mut buffer = vec![PING];
+        match self {
+            Self::Beta => {}
+            Self::V1_4 => buffer.push(PING_PAYLOAD),
+            Self::V1_6 {
+                protocol_version,
+                hostname,
+                port,
+            } => {
+                buffer.extend([PING_PAYLOAD, PLUGIN_MESSAGE]);
+                write_utf16(&mut buffer, PING_HOST_CHANNEL)?;
+                let host_len = hostname.encode_utf16().count();
+                buffer.write_u16::<BigEndian>((7 + 2 * host_len) as u16)?;
+                buffer.write_u8(*protocol_version)?;
+                write_utf16(&mut buffer, hostname)?;
+                buffer.write_i32::<BigEndian>(*port)?;
+            }
+        }
+        Ok(buffer)
+    }
+
+    /// Parses a complete ping request, including the leading `0xFE`.
+    pub fn parse(bytes: &[u8]) -> Result<Self> {
+        let mut reader = bytes;
+        if reader.read_u8()? != PING {
+            return Err(MooshroomError::InvalidLegacyPing("missing 0xFE"));
+        }
+        match reader.read_u8() {
+            Ok(PING_PAYLOAD) => {}
+            Ok(_) => return Err(MooshroomError::InvalidLegacyPing("expected 0x01")),
+            Err(_) => return Ok(Self::Beta),
+        }
+        match reader.read_u8() {
+            Ok(PLUGIN_MESSAGE) => {}
+            Ok(_) => return Err(MooshroomError::InvalidLegacyPing("expected 0xFA")),
+            Err(_) => return Ok(Self::V1_4),
+        }
+        if read_utf16(&mut reader)? != PING_HOST_CHANNEL {
+            return Err(MooshroomError::InvalidLegacyPing("expected MC|PingHost"));
+        }
+        let _data_len = reader.read_u16::<BigEndian>()?;
+        Ok(Self::V1_6 {
+            protocol_version: reader.read_u8()?,
+            hostname: read_utf16(&mut reader)?,
+            port: reader.read_i32::<BigEndian>()?,
+        })
+    }
+}
+
+impl LegacyStatus {
+    /// Encodes the kick packet a legacy server answers the ping with.
+    pub fn to_bytes(&self, ping: &LegacyPing) -> Result<Vec<u8>> {
+        let body = match ping {
+            LegacyPing::Beta => format!(
+                "{}\u{a7}{}\u{a7}{}",
+                self.motd, self.online_players, self.max_players
+            ),
+            _ => format!(
+                "\u{a7}1\0{}\0{}\0{}\0{}\0{}",
+                self.protocol_version,
+                self.version,
+                self.motd,
+                self.online_players,
+                self.max_players
+            ),
+        };
+        let mut buffer = vec![KICK];
+        write_utf16(&mut buffer, &body)?;
+        Ok(buffer)
+    }
+
+    pub fn read(reader: &mut impl Read) -> Result<Self> {
+        let id = reader.read_u8()?;
+        if id != KICK {
+            return Err(MooshroomError::UnexpectedPacket(KICK as i32, id as i32));
+        }
+        let body = read_utf16(reader)?;
+
+        if let Some(body) = body.strip_prefix("\u{a7}1\0") {
+            let mut parts = body.split('\0');
+            Ok(Self {
+                protocol_version: parse_number(parts.next())?,
+                version: parts.next().unwrap_or_default().into(),
+                motd: parts.next().unwrap_or_default().into(),
+                online_players: parse_number(parts.next())?,
+                max_players: parse_number(parts.next())?,
+            })
+        } else {
+            let mut parts = body.rsplitn(3, '\u{a7}');
+            let max_players = parse_number(parts.next())?;
+            let online_players = parse_number(parts.next())?;
+            Ok(Self {
+                motd: parts.next().unwrap_or_default().into(),
+                online_players,
+                max_players,
+                ..Default::default()
+            })
+        }
+    }
+}
+
+/// Sends a legacy ping over an open stream and reads the server's reply.
+pub fn ping_legacy(stream: &mut (impl Read + Write), ping: &LegacyPing) -> Result<LegacyStatus> {
+    stream.write_all(&ping.to_bytes()?)?;
+    stream.flush()?;
+    LegacyStatus::read(stream)
+}
+
+/// Connects to `hostname` and pings it using the 1.6 format, which older
+/// servers also understand. The hostname is sent along, as virtual hosts
+/// route on it.
+pub fn ping_legacy_server(hostname: &str, port: u16) -> Result<LegacyStatus> {
+    let mut stream = TcpStream::connect((hostname, port))?;
+    ping_legacy(
+        &mut stream,
+        &LegacyPing::V1_6 {
+            protocol_version: LEGACY_PROTOCOL_VERSION,
+            hostname: hostname.into(),
+            port: port as i32,
+        },
+    )
+}
+
+/// Checks whether a freshly accepted socket starts with a legacy ping.
+///
+/// Returns `None` without consuming anything if it does not, so the stream
+/// can be handed to `MooshroomProto` as normal.
+pub fn detect_legacy_ping(stream: &mut TcpStream) -> Result<Option<LegacyPing>> {
+    let mut first = [0; 1];
+    if stream.peek(&mut first)? == 0 {
+        return Err(MooshroomError::ConnectionClosed);
+    }
+    if first[0] != PING {
+        return Ok(None);
+    }
+
+    let timeout = stream.read_timeout()?;
+    stream.set_read_timeout(Some(DETECT_TIMEOUT))?;
+    let mut request = Vec::new();
+    let mut buffer = [0; 256];
+    let read = loop {
+        match stream.read(&mut buffer) {
+            Ok(0) => break Ok(()),
+            Ok(n) => {
+                request.extend_from_slice(&buffer[..n]);
+                // A shorter ping may just be split over segments, so only
+                // the timeout settles on one.
+                if LegacyPing::parse(&request)
+                    .is_ok_and(|p| !matches!(p, LegacyPing::Beta | LegacyPing::V1_4))
+                {
+                    break Ok(());
+                }
+            }
+            Err(e) if e.kind() == ErrorKind::Interrupted => {}
+            Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                break Ok(())
+            }
+            Err(e) => break Err(e),
+        }
+    };
+    stream.set_read_timeout(timeout)?;
+    read?;
+
+    LegacyPing::parse(&request).map(Some)
+}
+
+/// Answers a ping found by [`detect_legacy_ping`]. Legacy clients expect the
+/// server to close the connection afterwards.
+pub fn respond_legacy_ping(
+    stream: &mut impl Write,
+    ping: &LegacyPing,
+    status: &LegacyStatus,
+) -> Result<()> {
+    stream.write_all(&status.to_bytes(ping)?)?;
+    stream.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::TcpListener;
+
+    use super::*;
+
+    fn status() -> LegacyStatus {
+        LegacyStatus {
+            protocol_version: 74,
+            version: "1.6.4".into(),
+            motd: "A Minecraft Server".into(),
+            online_players: 3,
+            max_players: 20,
+        }
+    }
+
+    #[test]
+    fn test_ping_round_trip() {
+        let pings = [
+            LegacyPing::Beta,
+            LegacyPing::V1_4,
+            LegacyPing::V1_6 {
+                protocol_version: LEGACY_PROTOCOL_VERSION,
+                hostname: "localhost".into(),
+                port: 25565,
+            },
+        ];
+        for ping in pings {
+            assert_eq!(LegacyPing::parse(&ping.to_bytes().unwrap()).unwrap(), ping);
+        }
+    }
+
+    #[test]
+    fn test_status_formats() {
+        let modern = status().to_bytes(&LegacyPing::V1_4).unwrap();
+        assert_eq!(
+            LegacyStatus::read(&mut modern.as_slice()).unwrap(),
+            status()
+        );
+
+        let beta = status().to_bytes(&LegacyPing::Beta).unwrap();
+        assert_eq!(
+            LegacyStatus::read(&mut beta.as_slice()).unwrap(),
+            LegacyStatus {
+                motd: "A Minecraft Server".into(),
+                online_players: 3,
+                max_players: 20,
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_detect_and_respond() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let ping = detect_legacy_ping(&mut stream).unwrap().unwrap();
+            respond_legacy_ping(&mut stream, &ping, &status()).unwrap();
+            ping
+        });
+
+        assert_eq!(
+            ping_legacy_server("localhost", addr.port()).unwrap(),
+            status()
+        );
+        assert!(matches!(
+            server.join().unwrap(),
+            LegacyPing::V1_6 { hostname, port, .. }
+                if hostname == "localhost" && port == addr.port() as i32
+        ));
+    }
+
+    #[test]
+    fn test_detect_split_ping() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (mut stream, _) = listener.accept().unwrap();
+        client.set_nodelay(true).unwrap();
+        client.write_all(&[PING]).unwrap();
+        let detect = std::thread::spawn(move || detect_legacy_ping(&mut stream).unwrap());
+        std::thread::sleep(DETECT_TIMEOUT / 4);
+        client.write_all(&[PING_PAYLOAD]).unwrap();
+
+        assert_eq!(detect.join().unwrap(), Some(LegacyPing::V1_4));
+    }
+
+    #[test]
+    fn test_detect_modern_handshake() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (mut stream, _) = listener.accept().unwrap();
+        client.write_all(&[0x10, 0x00]).unwrap();
+
+        assert!(detect_legacy_ping(&mut stream).unwrap().is_none());
+        let mut untouched = [0; 2];
+        stream.read_exact(&mut untouched).unwrap();
+        assert_eq!(untouched, [0x10, 0x00]);
+    }
+}
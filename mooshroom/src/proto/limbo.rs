@@ -0,0 +1,407 @@
+use std::{
+    io::Write,
+    sync::{
+        atomic::{AtomicI32, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use super::server::{MooshroomServer, ServerConnection};
+use crate::{
+    client::metadata::KeepAliveResponse,
+    core::{
+        error::{MooshroomError, Result},
+        io::{MooshroomPacket, MooshroomWriteProto, DEFAULT_PROTOCAL_VERSION},
+        primitives::{Position, Vec3},
+        varint::VarInt,
+    },
+    proto::state::Play,
+    server::play::{
+        metadata::KeepAlive,
+        nbt::{NptCompound, NptNamedTag, NptTagData},
+        player::{SetDefaultSpawnPosition, SynchronizePlayerPosition},
+        world::{ChunkData, GameMode, LoginPlay, SetCenterChunk},
+    },
+};
+
+type Tag = NptTagData<DEFAULT_PROTOCAL_VERSION>;
+
+const KEEP_ALIVE_ID: VarInt =
+    <KeepAliveResponse as MooshroomPacket<DEFAULT_PROTOCAL_VERSION>>::PACKET_ID;
+
+pub const DIMENSION: &str = "minecraft:overworld";
+pub const BIOME: &str = "minecraft:plains";
+
+/// The limbo world spans y 0 to 255.
+pub const WORLD_HEIGHT: i32 = 256;
+const SECTIONS: usize = (WORLD_HEIGHT / 16) as usize;
+
+/// A holding world for players: a flat, optionally floored, patch of chunks
+/// around spawn where nothing happens besides keep-alives.
+pub struct Limbo {
+    spawn: Vec3<f64>,
+    floor: Option<i32>,
+    gamemode: GameMode,
+    view_distance: i32,
+    keep_alive_interval: Duration,
+    keep_alive_timeout: Duration,
+}
+
+impl Default for Limbo {
+    fn default() -> Self {
+        Self {
+            spawn: Vec3 {
+                x: 0.5,
+                y: 1.,
+                z: 0.5,
+            },
+            floor: Some(1),
+            gamemode: GameMode::Adventure,
+            view_distance: 2,
+            keep_alive_interval: Duration::from_secs(15),
+            keep_alive_timeout: Duration::from_secs(30),
+        }
+    }
+}
+
+impl Limbo {
+    pub fn spawn(mut self, spawn: Vec3<f64>) -> Self {
+        self.spawn = spawn;
+        self
+    }
+
+    /// The block state filling the bottom layer of the world, stone by
+    /// default. `None` leaves the world empty.
+    pub fn floor(mut self, block_state: Option<i32>) -> Self {
+        self.floor = block_state;
+        self
+    }
+
+    pub fn gamemode(mut self, gamemode: GameMode) -> Self {
+        self.gamemode = gamemode;
+        self
+    }
+
+    /// How many chunks around spawn are sent.
+    pub fn view_distance(mut self, chunks: i32) -> Self {
+        self.view_distance = chunks;
+        self
+    }
+
+    /// How often keep-alives are sent, and how long the client has to answer
+    /// one before it is kicked.
+    pub fn keep_alive(mut self, interval: Duration, timeout: Duration) -> Self {
+        self.keep_alive_interval = interval;
+        self.keep_alive_timeout = timeout;
+        self
+    }
+
+    /// Sends everything the client needs to leave the loading screen.
+    pub fn join<T: Write>(
+        &self,
+        conn: &mut ServerConnection<Play, T>,
+        entity_id: i32,
+    ) -> Result<()> {
+        conn.send(&LoginPlay {
+            entity_id,
+            gamemode: self.gamemode.clone(),
+            dimention_names: vec![DIMENSION.into()],
+            npt: registry_codec(),
+            dimention_type: DIMENSION.into(),
+            dimention_name: DIMENSION.into(),
+            max_players: 1.into(),
+            view_distance: self.view_distance.into(),
+            simulation_distance: self.view_distance.into(),
+            is_flat: true,
+            ..Default::default()
+        })?;
+        let block = Position {
+            x: self.spawn.x.floor() as i32,
+            y: self.spawn.y.floor() as i16,
+            z: self.spawn.z.floor() as i32,
+        };
+        conn.send(&SetDefaultSpawnPosition {
+            location: block,
+            angle: 0.,
+        })?;
+
+        let (center_x, center_z) = (block.x >> 4, block.z >> 4);
+        conn.send(&SetCenterChunk {
+            x: center_x.into(),
+            y: center_z.into(),
+        })?;
+        let data = self.chunk_sections()?;
+        for chunk_x in center_x - self.view_distance..=center_x + self.view_distance {
+            for chunk_z in center_z - self.view_distance..=center_z + self.view_distance {
+                conn.send(&ChunkData {
+                    chunk_x,
+                    chunk_z,
+                    heightmaps: NptCompound::new(Vec::new()),
+                    data: data.clone(),
+                    ..Default::default()
+                })?;
+            }
+        }
+
+        conn.send(&SynchronizePlayerPosition {
+            x: self.spawn.x,
+            y: self.spawn.y,
+            z: self.spawn.z,
+            teleport_id: 1.into(),
+            ..Default::default()
+        })
+    }
+
+    /// Keeps the player in limbo until they leave or stop answering
+    /// keep-alives.
+    pub fn run(&self, mut conn: ServerConnection<Play>) -> Result<()> {
+        conn.proto()
+            .set_read_timeout(Some(Duration::from_millis(50)))?;
+        let mut last_sent = Instant::now();
+        let mut pending: Option<i64> = None;
+        let mut next_id = 0;
+        loop {
+            if let Some(id) = pending {
+                if last_sent.elapsed() >= self.keep_alive_timeout {
+                    log::debug!("{} did not answer keep-alive {id}", conn.state().username);
+                    return conn.disconnect("Timed out");
+                }
+            } else if last_sent.elapsed() >= self.keep_alive_interval {
+                conn.send(&KeepAlive(next_id))?;
+                pending = Some(next_id);
+                next_id += 1;
+                last_sent = Instant::now();
+            }
+
+            // Vanilla clients send plenty this library has no packet for,
+            // so anything but a keep-alive is skipped undecoded.
+            let raw = match conn.try_read_raw() {
+                Ok(Some(raw)) => raw,
+                Ok(None) => continue,
+                Err(MooshroomError::ConnectionClosed) => return Ok(()),
+                Err(e) => return Err(e),
+            };
+            if raw.id == KEEP_ALIVE_ID {
+                match raw.decode::<DEFAULT_PROTOCAL_VERSION, KeepAliveResponse>() {
+                    Ok(p) if pending == Some(p.0) => pending = None,
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    /// Accepts players from `server` forever, each on its own thread.
+    pub fn serve(self, mut server: MooshroomServer) -> Result<()> {
+        let limbo = Arc::new(self);
+        let entity_ids = AtomicI32::new(1);
+        loop {
+            let mut conn = server.accept()?;
+            let limbo = limbo.clone();
+            let entity_id = entity_ids.fetch_add(1, Ordering::Relaxed);
+            std::thread::spawn(move || {
+                let username = conn.state().username.clone();
+                let result = limbo
+                    .join(&mut conn, entity_id)
+                    .and_then(|_| limbo.run(conn));
+                if let Err(e) = result {
+                    log::debug!("{username} left limbo: {e}");
+                }
+            });
+        }
+    }
+
+    /// The sections of every chunk, bottom to top. Only the floor layer, if
+    /// any, has blocks.
+    fn chunk_sections(&self) -> Result<Vec<u8>> {
+        let mut data = Vec::new();
+        for section in 0..SECTIONS {
+            match self.floor {
+                Some(floor) if section == 0 => {
+                    256i16.write_proto::<DEFAULT_PROTOCAL_VERSION>(&mut data)?;
+                    // Four bits per block, with air and the floor in the palette.
+                    4u8.write_proto::<DEFAULT_PROTOCAL_VERSION>(&mut data)?;
+                    vec![VarInt(0), VarInt(floor)]
+                        .write_proto::<DEFAULT_PROTOCAL_VERSION>(&mut data)?;
+                    let mut longs = vec![0u64; 4096 / 16];
+                    longs[..256 / 16].fill(0x1111_1111_1111_1111);
+                    longs.write_proto::<DEFAULT_PROTOCAL_VERSION>(&mut data)?;
+                }
+                _ => {
+                    0i16.write_proto::<DEFAULT_PROTOCAL_VERSION>(&mut data)?;
+                    write_single_value(&mut data, 0)?;
+                }
+            }
+            // Every biome is plains, the only one in the registry.
+            write_single_value(&mut data, 0)?;
+        }
+        Ok(data)
+    }
+}
+
+/// A paletted container holding a single value.
+fn write_single_value(data: &mut Vec<u8>, value: i32) -> Result<()> {
+    0u8.write_proto::<DEFAULT_PROTOCAL_VERSION>(data)?;
+    VarInt(value).write_proto::<DEFAULT_PROTOCAL_VERSION>(data)?;
+    VarInt(0).write_proto::<DEFAULT_PROTOCAL_VERSION>(data)
+}
+
+fn named<const N: usize>(tags: [(&str, Tag); N]) -> Vec<NptNamedTag<DEFAULT_PROTOCAL_VERSION>> {
+    tags.into_iter()
+        .map(|(name, tag)| NptNamedTag::new(name, tag))
+        .collect()
+}
+
+fn compound<const N: usize>(tags: [(&str, Tag); N]) -> Tag {
+    Tag::Compount(named(tags))
+}
+
+fn string(s: &str) -> Tag {
+    Tag::String(s.into())
+}
+
+fn registry(ty: &str, name: &str, element: Tag) -> Tag {
+    let entry = compound([
+        ("name", string(name)),
+        ("id", Tag::Int(0)),
+        ("element", element),
+    ]);
+    compound([("type", string(ty)), ("value", Tag::List(vec![entry]))])
+}
+
+fn chat_decoration(translation_key: &str) -> Tag {
+    compound([
+        ("translation_key", string(translation_key)),
+        (
+            "parameters",
+            Tag::List(vec![string("sender"), string("content")]),
+        ),
+    ])
+}
+
+/// The registries sent in `LoginPlay`: one dimension type, one biome and the
+/// chat type for player chat, which is all the client needs.
+pub fn registry_codec() -> NptCompound {
+    let dimension = compound([
+        ("piglin_safe", Tag::Byte(0)),
+        ("natural", Tag::Byte(1)),
+        // Full brightness everywhere, so no light data needs to be sent.
+        ("ambient_light", Tag::Float(1.)),
+        ("monster_spawn_block_light_limit", Tag::Int(0)),
+        ("infiniburn", string("#minecraft:infiniburn_overworld")),
+        ("respawn_anchor_works", Tag::Byte(0)),
+        ("has_skylight", Tag::Byte(1)),
+        ("bed_works", Tag::Byte(1)),
+        ("effects", string(DIMENSION)),
+        ("has_raids", Tag::Byte(0)),
+        ("logical_height", Tag::Int(WORLD_HEIGHT)),
+        ("coordinate_scale", Tag::Double(1.)),
+        ("monster_spawn_light_level", Tag::Int(0)),
+        ("min_y", Tag::Int(0)),
+        ("ultrawarm", Tag::Byte(0)),
+        ("has_ceiling", Tag::Byte(0)),
+        ("height", Tag::Int(WORLD_HEIGHT)),
+    ]);
+    let biome = compound([
+        ("precipitation", string("none")),
+        ("temperature", Tag::Float(0.8)),
+        ("downfall", Tag::Float(0.4)),
+        (
+            "effects",
+            compound([
+                ("sky_color", Tag::Int(0x78a7ff)),
+                ("water_fog_color", Tag::Int(0x050533)),
+                ("fog_color", Tag::Int(0xc0d8ff)),
+                ("water_color", Tag::Int(0x3f76e4)),
+            ]),
+        ),
+    ]);
+    let chat = compound([
+        ("chat", chat_decoration("chat.type.text")),
+        ("narration", chat_decoration("chat.type.text.narrate")),
+    ]);
+    NptCompound::new(named([
+        (
+            "minecraft:dimension_type",
+            registry("minecraft:dimension_type", DIMENSION, dimension),
+        ),
+        (
+            "minecraft:worldgen/biome",
+            registry("minecraft:worldgen/biome", BIOME, biome),
+        ),
+        (
+            "minecraft:chat_type",
+            registry("minecraft:chat_type", "minecraft:chat", chat),
+        ),
+    ]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        client::login::LoginStart,
+        proto::{codec::RawPacket, state::Connection},
+        server::play::PlayStage,
+    };
+
+    #[test]
+    fn test_limbo_join() {
+        let server = MooshroomServer::bind("127.0.0.1:0")
+            .unwrap()
+            .compression_threshold(Some(256));
+        let addr = server.local_addr().unwrap();
+        let limbo = Limbo::default()
+            .view_distance(1)
+            .keep_alive(Duration::ZERO, Duration::from_secs(5));
+        std::thread::spawn(move || {
+            let _ = limbo.serve(server);
+        });
+
+        let start = LoginStart {
+            name: "Steve".into(),
+            ..Default::default()
+        };
+        let mut conn = Connection::connect(addr)
+            .unwrap()
+            .login("localhost", addr.port())
+            .unwrap()
+            .login_offline(&start)
+            .unwrap();
+
+        let mut chunks = 0;
+        loop {
+            match conn.read().unwrap() {
+                PlayStage::Login(p) => {
+                    assert_eq!(p.npt, registry_codec());
+                    assert_eq!(p.dimention_type, DIMENSION);
+                }
+                PlayStage::ChunkData(p) => {
+                    assert_eq!(p.data, Limbo::default().chunk_sections().unwrap());
+                    chunks += 1;
+                }
+                PlayStage::SynchronizePlayerPosition(p) => {
+                    assert_eq!((p.x, p.y, p.z), (0.5, 1., 0.5));
+                    break;
+                }
+                _ => {}
+            }
+        }
+        assert_eq!(chunks, 9);
+
+        let PlayStage::KeepAlive(KeepAlive(id)) = conn.read().unwrap() else {
+            panic!("expected a keep-alive");
+        };
+
+        // Packets limbo has no use for, like command suggestions, are skipped.
+        let mut proto = conn.into_proto();
+        proto
+            .write_raw(&RawPacket::new(0x09.into(), &[1, 1, b'/'][..]))
+            .unwrap();
+        proto.write_packet(&KeepAliveResponse(id)).unwrap();
+        let PlayStage::KeepAlive(KeepAlive(next)) = proto.read_one_of().unwrap() else {
+            panic!("expected a keep-alive");
+        };
+        assert_eq!(next, id + 1);
+    }
+}
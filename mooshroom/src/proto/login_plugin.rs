@@ -0,0 +1,39 @@
+use std::collections::HashMap;
+
+use crate::{
+    client::login::LoginPluginResponse,
+    core::error::Result,
+    server::login::LoginPluginRequest,
+};
+
+type Handler = Box<dyn FnMut(&[u8]) -> Result<Option<Vec<u8>>> + Send>;
+
+/// Answers login plugin requests by channel. Requests on channels without a
+/// handler get the "not understood" response, as the vanilla client does.
+#[derive(Default)]
+pub struct LoginPlugins {
+    handlers: HashMap<String, Handler>,
+}
+
+impl LoginPlugins {
+    /// Registers `handler` for `channel`, replacing any previous one. The
+    /// handler returns the response payload, or `None` to decline.
+    pub fn register(
+        &mut self,
+        channel: impl Into<String>,
+        handler: impl FnMut(&[u8]) -> Result<Option<Vec<u8>>> + Send + 'static,
+    ) {
+        self.handlers.insert(channel.into(), Box::new(handler));
+    }
+
+    pub fn respond(&mut self, request: &LoginPluginRequest) -> Result<LoginPluginResponse> {
+        let data = match self.handlers.get_mut(&request.channel) {
+            Some(handler) => handler(&request.data.0)?,
+            None => None,
+        };
+        Ok(LoginPluginResponse {
+            message_id: request.message_id,
+            data: data.map(Into::into),
+        })
+    }
+}
@@ -1,14 +1,64 @@
+pub mod auth;
+pub mod chat;
+pub mod chat_security;
 pub mod codec;
 pub mod connection;
+pub mod decode;
+pub mod events;
+pub mod interceptor;
+pub mod inventory;
+pub mod legacy;
+pub mod limbo;
+pub mod login_plugin;
+pub mod movement;
+pub mod ping;
+pub mod plugin_channels;
+pub mod reconnect;
+pub mod recording;
+pub mod server;
+pub mod split;
+pub mod state;
+pub mod status;
+#[cfg(any(test, feature = "test-util"))]
+pub mod testing;
+pub mod velocity;
 
-use codec::MooshroomCodec;
+use std::{
+    any::Any,
+    collections::VecDeque,
+    fmt::Debug,
+    io::ErrorKind,
+    net::TcpStream,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use bytes::{Buf, BytesMut};
+use codec::{MooshroomCodec, RawPacket};
+use interceptor::{Decoded, Direction, Intercept, InterceptContext, Interceptor};
+use log::error;
 use mooshroom_core::data::MooshroomCollection;
 
 use crate::core::{error::*, io::*};
 
+/// Interceptors and the packets they inject, shared between split halves so
+/// only the writing half ever writes to the socket.
+#[derive(Default)]
+struct Hooks {
+    interceptors: Mutex<Vec<Box<dyn Interceptor>>>,
+    outgoing: Mutex<VecDeque<RawPacket>>,
+}
+
 pub struct MooshroomProto<T> {
     inner: T,
     pub codec: MooshroomCodec<DEFAULT_PROTOCAL_VERSION>,
+    tx_buffer: BytesMut,
+    nonblocking: bool,
+    hooks: Arc<Hooks>,
+    injected: VecDeque<RawPacket>,
+    /// Sends what interceptors injected while reading. Only set on a proto
+    /// that can write; a split reader leaves that to its writer.
+    after_read: Option<fn(&mut Self) -> Result<()>>,
 }
 
 impl<T> MooshroomProto<T> {
@@ -16,78 +66,395 @@ impl<T> MooshroomProto<T> {
         Self {
             inner,
             codec: MooshroomCodec::new(),
+            tx_buffer: BytesMut::new(),
+            nonblocking: false,
+            hooks: Default::default(),
+            injected: VecDeque::new(),
+            after_read: None,
         }
     }
 
     pub const fn protocal_version(&self) -> i32 {
         self.codec.protocal_version()
     }
+
+    /// Number of encoded bytes still waiting to be written to the socket.
+    pub fn pending_write(&self) -> usize {
+        self.tx_buffer.len()
+    }
+
+    fn has_interceptors(&self) -> bool {
+        !self.hooks.interceptors.lock().unwrap().is_empty()
+    }
+
+    /// Runs the interceptors over a packet, returning the packet to use or
+    /// `None` if it was dropped.
+    fn intercept(&mut self, direction: Direction, packet: RawPacket) -> Option<RawPacket> {
+        let mut ctx = InterceptContext::default();
+        let mut packet = Some(packet);
+        for interceptor in self.hooks.interceptors.lock().unwrap().iter_mut() {
+            let p = match &packet {
+                Some(p) => p,
+                None => break,
+            };
+            match interceptor.on_raw(direction, p, &mut ctx) {
+                Intercept::Forward => {}
+                Intercept::Drop => packet = None,
+                Intercept::Replace(r) => packet = Some(r),
+            }
+        }
+        self.injected.extend(ctx.incoming);
+        self.hooks.outgoing.lock().unwrap().extend(ctx.outgoing);
+        packet
+    }
+
+    fn inspect<P: Any + Debug>(&mut self, direction: Direction, packet: &P) {
+        let decoded = Decoded::new(packet);
+        for interceptor in self.hooks.interceptors.lock().unwrap().iter_mut() {
+            interceptor.on_decoded(direction, &decoded);
+        }
+    }
+
+    fn next_raw(&mut self) -> Result<Option<RawPacket>> {
+        if let Some(p) = self.injected.pop_front() {
+            return Ok(Some(p));
+        }
+        while let Some(raw) = self.codec.read_raw()? {
+            if !self.has_interceptors() {
+                return Ok(Some(raw));
+            }
+            if let Some(raw) = self.intercept(Direction::Incoming, raw) {
+                return Ok(Some(raw));
+            }
+        }
+        Ok(None)
+    }
+
+    fn next_packet<P>(&mut self) -> Result<Option<P>>
+    where
+        P: MooshroomPacket<DEFAULT_PROTOCAL_VERSION> + Debug + 'static,
+    {
+        if self.injected.is_empty() && !self.has_interceptors() {
+            return self.codec.read_packet();
+        }
+        let p = match self.next_raw()? {
+            Some(raw) => raw.decode::<DEFAULT_PROTOCAL_VERSION, P>()?,
+            None => return Ok(None),
+        };
+        self.inspect(Direction::Incoming, &p);
+        Ok(Some(p))
+    }
+
+    fn next_one_of<P>(&mut self) -> Result<Option<P>>
+    where
+        P: MooshroomCollection<DEFAULT_PROTOCAL_VERSION> + Debug + 'static,
+    {
+        if self.injected.is_empty() && !self.has_interceptors() {
+            return self.codec.read_one_of();
+        }
+        let raw = match self.next_raw()? {
+            Some(raw) => raw,
+            None => return Ok(None),
+        };
+        let p = raw
+            .decode_one_of::<DEFAULT_PROTOCAL_VERSION, P>()
+            .map_err(|e| {
+                error!(
+                    "Failed to read one of {} with packet id 0x{:x}. {}",
+                    std::any::type_name::<P>(),
+                    raw.id.0,
+                    e
+                );
+                e
+            })?;
+        self.inspect(Direction::Incoming, &p);
+        Ok(Some(p))
+    }
+}
+
+impl MooshroomProto<TcpStream> {
+    pub fn set_read_timeout(&self, timeout: Option<Duration>) -> Result<()> {
+        self.inner.set_read_timeout(timeout)?;
+        Ok(())
+    }
+
+    pub fn set_write_timeout(&self, timeout: Option<Duration>) -> Result<()> {
+        self.inner.set_write_timeout(timeout)?;
+        Ok(())
+    }
+
+    /// In non-blocking mode the `try_*` readers return `Ok(None)` when no full
+    /// packet is available yet, and writes that would block stay buffered
+    /// until [`MooshroomProto::flush`] is called.
+    pub fn set_nonblocking(&mut self, nonblocking: bool) -> Result<()> {
+        self.inner.set_nonblocking(nonblocking)?;
+        self.nonblocking = nonblocking;
+        Ok(())
+    }
+}
+
+fn is_timeout(e: &std::io::Error) -> bool {
+    matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut)
 }
 
 impl<R> MooshroomProto<R>
 where
     R: std::io::Read,
 {
-    pub fn buffer_read(&mut self) -> Result<()> {
+    /// Reads once from the socket into the codec.
+    /// Returns `false` if the read would block or timed out.
+    fn fill(&mut self) -> Result<bool> {
         let mut buffer = [0; 1024];
         loop {
-            let n = self.inner.read(&mut buffer)?;
-            if n > 0 {
-                self.codec.add_bytes(&buffer[..n]);
-                return Ok(());
+            match self.inner.read(&mut buffer) {
+                Ok(0) => return Err(MooshroomError::ConnectionClosed),
+                Ok(n) => {
+                    self.codec.add_bytes(&buffer[..n]);
+                    return Ok(true);
+                }
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) if is_timeout(&e) => return Ok(false),
+                Err(e) => return Err(e.into()),
             }
         }
     }
-    pub fn read_packet<T: MooshroomPacket<DEFAULT_PROTOCAL_VERSION>>(&mut self) -> Result<T> {
-        let mut buffer = [0; 1024];
+
+    fn read_with<P>(&mut self, mut read: impl FnMut(&mut Self) -> Result<Option<P>>) -> Result<P> {
         loop {
-            if let Some(p) = self.codec.read_packet()? {
+            if let Some(p) = read(self)? {
+                self.after_read()?;
                 return Ok(p);
             }
-            let n = self.inner.read(&mut buffer)?;
-            if n > 0 {
-                self.codec.add_bytes(&buffer[..n]);
+            if !self.fill()? {
+                return Err(MooshroomError::TimedOut);
             }
         }
     }
 
-    pub fn read_one_of<T: MooshroomCollection<DEFAULT_PROTOCAL_VERSION>>(&mut self) -> Result<T> {
-        let mut buffer = [0; 1024];
+    fn try_read_with<P>(
+        &mut self,
+        mut read: impl FnMut(&mut Self) -> Result<Option<P>>,
+    ) -> Result<Option<P>> {
         loop {
-            if let Some(p) = self.codec.read_one_of()? {
-                return Ok(p);
+            if let Some(p) = read(self)? {
+                self.after_read()?;
+                return Ok(Some(p));
             }
-            let n = self.inner.read(&mut buffer)?;
-            if n > 0 {
-                self.codec.add_bytes(&buffer[..n]);
+            if !self.fill()? {
+                return Ok(None);
             }
         }
     }
+
+    fn after_read(&mut self) -> Result<()> {
+        match self.after_read {
+            Some(after_read) => after_read(self),
+            None => Ok(()),
+        }
+    }
+
+    pub fn buffer_read(&mut self) -> Result<()> {
+        if self.fill()? {
+            Ok(())
+        } else {
+            Err(MooshroomError::TimedOut)
+        }
+    }
+
+    pub fn read_packet<T>(&mut self) -> Result<T>
+    where
+        T: MooshroomPacket<DEFAULT_PROTOCAL_VERSION> + Debug + 'static,
+    {
+        self.read_with(|p| p.next_packet())
+    }
+
+    pub fn read_raw(&mut self) -> Result<RawPacket> {
+        self.read_with(|p| p.next_raw())
+    }
+
+    pub fn read_one_of<T>(&mut self) -> Result<T>
+    where
+        T: MooshroomCollection<DEFAULT_PROTOCAL_VERSION> + Debug + 'static,
+    {
+        self.read_with(|p| p.next_one_of())
+    }
+
+    pub fn try_read_packet<T>(&mut self) -> Result<Option<T>>
+    where
+        T: MooshroomPacket<DEFAULT_PROTOCAL_VERSION> + Debug + 'static,
+    {
+        self.try_read_with(|p| p.next_packet())
+    }
+
+    pub fn try_read_raw(&mut self) -> Result<Option<RawPacket>> {
+        self.try_read_with(|p| p.next_raw())
+    }
+
+    pub fn try_read_one_of<T>(&mut self) -> Result<Option<T>>
+    where
+        T: MooshroomCollection<DEFAULT_PROTOCAL_VERSION> + Debug + 'static,
+    {
+        self.try_read_with(|p| p.next_one_of())
+    }
 }
 
 impl<T> MooshroomProto<T>
 where
     T: std::io::Write,
 {
-    pub fn write_packet(
-        &mut self,
-        p: &impl MooshroomPacket<DEFAULT_PROTOCAL_VERSION>,
-    ) -> Result<()> {
-        let bytes = self.codec.encode(p)?;
-        self.inner.write_all(&bytes)?;
+    /// Adds an interceptor that is run after the ones already added. Packets
+    /// it injects towards the peer are sent at the end of the read or write
+    /// that produced them.
+    pub fn add_interceptor(&mut self, interceptor: impl Interceptor + 'static) {
+        self.hooks
+            .interceptors
+            .lock()
+            .unwrap()
+            .push(Box::new(interceptor));
+        self.after_read = Some(Self::send_injected);
+    }
+
+    /// Writes as much of the buffered output as the socket accepts.
+    /// Returns `true` once everything has been written.
+    pub fn flush(&mut self) -> Result<bool> {
+        while !self.tx_buffer.is_empty() {
+            match self.inner.write(&self.tx_buffer) {
+                Ok(0) => return Err(MooshroomError::ConnectionClosed),
+                Ok(n) => self.tx_buffer.advance(n),
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) if is_timeout(&e) && self.nonblocking => return Ok(false),
+                Err(e) if is_timeout(&e) => return Err(MooshroomError::TimedOut),
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Ok(true)
+    }
+
+    fn write_bytes(&mut self, bytes: &[u8]) -> Result<()> {
+        self.queue_injected()?;
+        self.tx_buffer.extend_from_slice(bytes);
+        self.flush()?;
+        Ok(())
+    }
+
+    fn send_injected(&mut self) -> Result<()> {
+        self.queue_injected()?;
+        self.flush()?;
         Ok(())
     }
+
+    /// Encodes packets injected by interceptors ahead of the next write.
+    fn queue_injected(&mut self) -> Result<()> {
+        let injected = std::mem::take(&mut *self.hooks.outgoing.lock().unwrap());
+        for p in injected {
+            let bytes = self.codec.encode_raw(&p)?;
+            self.tx_buffer.extend_from_slice(&bytes);
+        }
+        Ok(())
+    }
+
+    pub fn write_packet<P>(&mut self, p: &P) -> Result<()>
+    where
+        P: MooshroomPacket<DEFAULT_PROTOCAL_VERSION> + Debug + 'static,
+    {
+        if !self.has_interceptors() {
+            let bytes = self.codec.encode(p)?;
+            return self.write_bytes(&bytes);
+        }
+        self.inspect(Direction::Outgoing, p);
+        self.write_raw(&RawPacket::from_packet::<DEFAULT_PROTOCAL_VERSION, _>(p)?)
+    }
+
+    pub fn write_raw(&mut self, p: &RawPacket) -> Result<()> {
+        let p = if !self.has_interceptors() {
+            p.clone()
+        } else {
+            match self.intercept(Direction::Outgoing, p.clone()) {
+                Some(p) => p,
+                None => return self.send_injected(),
+            }
+        };
+        let bytes = self.codec.encode_raw(&p)?;
+        self.write_bytes(&bytes)
+    }
 }
 
 impl<T> MooshroomProto<T>
 where
     T: std::io::Read + std::io::Write,
 {
-    pub fn send_command<P: MooshroomCommand<DEFAULT_PROTOCAL_VERSION>>(
-        &mut self,
-        p: &P,
-    ) -> Result<P::Response> {
+    pub fn send_command<P>(&mut self, p: &P) -> Result<P::Response>
+    where
+        P: MooshroomCommand<DEFAULT_PROTOCAL_VERSION> + Debug + 'static,
+        P::Response: Debug + 'static,
+    {
         self.write_packet(p)?;
         self.read_packet()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::Write,
+        net::{TcpListener, TcpStream},
+    };
+
+    use super::*;
+    use crate::server::play::metadata::KeepAlive;
+
+    fn socket_pair() -> (TcpStream, TcpStream) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (server, _) = listener.accept().unwrap();
+        (client, server)
+    }
+
+    #[test]
+    fn test_read_after_close() {
+        let (client, server) = socket_pair();
+        drop(server);
+        let mut proto = MooshroomProto::new(client);
+        assert!(matches!(
+            proto.read_packet::<KeepAlive>(),
+            Err(MooshroomError::ConnectionClosed)
+        ));
+    }
+
+    #[test]
+    fn test_read_timeout() {
+        let (client, _server) = socket_pair();
+        let mut proto = MooshroomProto::new(client);
+        proto
+            .set_read_timeout(Some(Duration::from_millis(10)))
+            .unwrap();
+        assert!(matches!(
+            proto.read_packet::<KeepAlive>(),
+            Err(MooshroomError::TimedOut)
+        ));
+    }
+
+    #[test]
+    fn test_nonblocking_read() {
+        let (client, mut server) = socket_pair();
+        let mut proto = MooshroomProto::new(client);
+        proto.set_nonblocking(true).unwrap();
+        assert!(proto.try_read_packet::<KeepAlive>().unwrap().is_none());
+
+        let bytes = MooshroomCodec::<DEFAULT_PROTOCAL_VERSION>::new()
+            .encode(&KeepAlive(7))
+            .unwrap();
+        server.write_all(&bytes[..1]).unwrap();
+        server.flush().unwrap();
+        std::thread::sleep(Duration::from_millis(10));
+        assert!(proto.try_read_packet::<KeepAlive>().unwrap().is_none());
+
+        server.write_all(&bytes[1..]).unwrap();
+        let p = loop {
+            if let Some(p) = proto.try_read_packet::<KeepAlive>().unwrap() {
+                break p;
+            }
+        };
+        assert_eq!(p.0, 7);
+    }
+}
@@ -0,0 +1,149 @@
+use mooshroom_core::primitives::Vec3;
+
+use crate::{
+    client::movement::{
+        SetPlayerOnGround,
+        SetPlayerPosition,
+        SetPlayerPositionAndRotation,
+        SetPlayerRotation,
+    },
+    server::play::player::SynchronizePlayerPosition,
+};
+
+/// The vanilla client sends its position at least this often, even when
+/// standing still.
+pub const POSITION_REMINDER_TICKS: u32 = 20;
+
+/// Movements shorter than this are not sent.
+const MIN_MOVEMENT: f64 = 2.0E-4;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MovementPacket {
+    Position(SetPlayerPosition),
+    PositionAndRotation(SetPlayerPositionAndRotation),
+    Rotation(SetPlayerRotation),
+    OnGround(SetPlayerOnGround),
+}
+
+/// Tracks the player's position and what was last sent to the server, so
+/// each tick only the packet that is needed gets sent.
+#[derive(Debug, Clone, Default)]
+pub struct MovementTracker {
+    pub position: Vec3<f64>,
+    pub yaw: f32,
+    pub pitch: f32,
+    pub on_ground: bool,
+
+    last_position: Vec3<f64>,
+    last_yaw: f32,
+    last_pitch: f32,
+    last_on_ground: bool,
+    ticks_since_position: u32,
+    force_full: bool,
+}
+
+impl MovementTracker {
+    /// Applies a server teleport. The next tick always sends position and
+    /// rotation, as the vanilla client does after confirming it.
+    pub fn apply_teleport(&mut self, p: &SynchronizePlayerPosition) {
+        let f = &p.flags;
+        let relative = |is_relative: bool, current: f64, value: f64| {
+            if is_relative {
+                current + value
+            } else {
+                value
+            }
+        };
+        self.position = Vec3 {
+            x: relative(f.x_is_relitive, self.position.x, p.x),
+            y: relative(f.y_is_relitive, self.position.y, p.y),
+            z: relative(f.z_is_relitive, self.position.z, p.z),
+        };
+        self.yaw = relative(f.y_rot_is_relitive, self.yaw as f64, p.yaw as f64) as f32;
+        self.pitch = relative(f.x_rot_is_relitive, self.pitch as f64, p.pitch as f64) as f32;
+        self.force_full = true;
+    }
+
+    /// Returns the packet to send this tick, if any, and records it as sent.
+    pub fn tick(&mut self) -> Option<MovementPacket> {
+        self.ticks_since_position += 1;
+
+        let d = Vec3 {
+            x: self.position.x - self.last_position.x,
+            y: self.position.y - self.last_position.y,
+            z: self.position.z - self.last_position.z,
+        };
+        let moved = self.force_full
+            || d.x * d.x + d.y * d.y + d.z * d.z > MIN_MOVEMENT * MIN_MOVEMENT
+            || self.ticks_since_position >= POSITION_REMINDER_TICKS;
+        let rotated = self.force_full || self.yaw != self.last_yaw || self.pitch != self.last_pitch;
+
+        let Vec3 { x, y, z } = self.position;
+        let (yaw, pitch, on_ground) = (self.yaw, self.pitch, self.on_ground);
+        let packet = match (moved, rotated) {
+            (true, true) => MovementPacket::PositionAndRotation(SetPlayerPositionAndRotation {
+                x,
+                y,
+                z,
+                yaw,
+                pitch,
+                on_ground,
+            }),
+            (true, false) => MovementPacket::Position(SetPlayerPosition { x, y, z, on_ground }),
+            (false, true) => MovementPacket::Rotation(SetPlayerRotation {
+                yaw,
+                pitch,
+                on_ground,
+            }),
+            (false, false) if on_ground != self.last_on_ground => {
+                MovementPacket::OnGround(SetPlayerOnGround { on_ground })
+            }
+            (false, false) => return None,
+        };
+
+        if moved {
+            self.last_position = self.position;
+            self.ticks_since_position = 0;
+        }
+        if rotated {
+            self.last_yaw = yaw;
+            self.last_pitch = pitch;
+        }
+        self.last_on_ground = on_ground;
+        self.force_full = false;
+        Some(packet)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_minimal_packets() {
+        let mut m = MovementTracker::default();
+        m.apply_teleport(&SynchronizePlayerPosition {
+            x: 10.,
+            y: 64.,
+            z: 10.,
+            ..Default::default()
+        });
+        assert!(matches!(
+            m.tick(),
+            Some(MovementPacket::PositionAndRotation(p)) if p.y == 64.
+        ));
+        assert_eq!(m.tick(), None);
+
+        m.position.x += 0.5;
+        assert!(matches!(m.tick(), Some(MovementPacket::Position(_))));
+        m.yaw = 90.;
+        assert!(matches!(m.tick(), Some(MovementPacket::Rotation(_))));
+        m.on_ground = true;
+        assert!(matches!(m.tick(), Some(MovementPacket::OnGround(_))));
+
+        for _ in 0..POSITION_REMINDER_TICKS - 3 {
+            assert_eq!(m.tick(), None);
+        }
+        assert!(matches!(m.tick(), Some(MovementPacket::Position(_))));
+    }
+}
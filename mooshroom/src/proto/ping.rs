@@ -0,0 +1,96 @@
+use std::time::Duration;
+
+use super::state::Connection;
+use crate::{core::error::Result, server::status::StatusBody};
+
+pub const DEFAULT_PORT: u16 = 25565;
+
+/// The result of a server list ping.
+#[derive(Debug, Clone)]
+pub struct ServerPing {
+    pub status: StatusBody,
+    pub latency: Duration,
+}
+
+/// Splits `host`, `host:port` or `[ipv6]:port`, defaulting to port 25565.
+fn split_address(addr: &str) -> (&str, u16) {
+    if let Some((host, port)) = addr.rsplit_once(':') {
+        if let Ok(port) = port.parse() {
+            if !host.contains(':') || host.starts_with('[') {
+                return (host.trim_start_matches('[').trim_end_matches(']'), port);
+            }
+        }
+    }
+    (addr, DEFAULT_PORT)
+}
+
+/// Pings `addr` the way the server list does: handshake, status request,
+/// then a ping to measure latency.
+pub fn ping_server(addr: &str) -> Result<ServerPing> {
+    let (host, port) = split_address(addr);
+    let mut conn = Connection::connect((host, port))?.status(host, port)?;
+    let status = conn.request_status()?.response.into_inner();
+    let latency = conn.ping()?;
+    Ok(ServerPing { status, latency })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::TcpListener;
+
+    use super::*;
+    use crate::{
+        client::{
+            handshake::Handshake,
+            status::{PingRequest, StatusRequest},
+        },
+        containers::Json,
+        proto::MooshroomProto,
+        server::status::{PingResponse, StatusResponse},
+    };
+
+    #[test]
+    fn test_split_address() {
+        assert_eq!(split_address("example.com"), ("example.com", 25565));
+        assert_eq!(split_address("example.com:25566"), ("example.com", 25566));
+        assert_eq!(split_address("[::1]:25566"), ("::1", 25566));
+        assert_eq!(split_address("::1"), ("::1", 25565));
+    }
+
+    #[test]
+    fn test_ping_server() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = std::thread::spawn(move || {
+            let mut proto = MooshroomProto::new(listener.accept().unwrap().0);
+            let handshake: Handshake = proto.read_packet().unwrap();
+            let _: StatusRequest = proto.read_packet().unwrap();
+            let mut body: StatusBody = serde_json::from_str(
+                r#"{
+                    "version": {"name": "1.19.2", "protocol": 760},
+                    "players": {"max": 20, "online": 1, "sample": [
+                        {"name": "Steve", "id": "4566e69f-c907-48ee-8d71-d7ba5aa00d20"}
+                    ]},
+                    "description": {"text": "Hello", "extra": [" world"]}
+                }"#,
+            )
+            .unwrap();
+            body.set_favicon_png(b"\x89PNG");
+            proto
+                .write_packet(&StatusResponse {
+                    response: Json::new(body),
+                })
+                .unwrap();
+            let ping: PingRequest = proto.read_packet().unwrap();
+            proto.write_packet(&PingResponse(ping.0)).unwrap();
+            handshake
+        });
+
+        let ping = ping_server(&addr.to_string()).unwrap();
+        assert_eq!(ping.status.version.protocol, 760);
+        assert_eq!(ping.status.description.to_plain(), "Hello world");
+        assert_eq!(ping.status.players.sample[0].name, "Steve");
+        assert_eq!(ping.status.favicon_png().unwrap().unwrap(), b"\x89PNG");
+        assert_eq!(server.join().unwrap().server_port, addr.port());
+    }
+}
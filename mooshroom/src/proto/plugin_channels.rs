@@ -0,0 +1,166 @@
+use std::collections::{BTreeSet, HashMap};
+
+use crate::{
+    client::metadata::PluginMessage as ClientPluginMessage,
+    core::{
+        error::Result,
+        io::{MooshroomReadProto, MooshroomWriteProto, DEFAULT_PROTOCAL_VERSION},
+    },
+    server::play::metadata::PluginMessage,
+};
+
+pub const REGISTER: &str = "minecraft:register";
+pub const UNREGISTER: &str = "minecraft:unregister";
+pub const BRAND: &str = "minecraft:brand";
+
+type Handler = Box<dyn FnMut(&[u8]) -> Result<Option<Vec<u8>>> + Send>;
+
+/// Plugin channels for the play state: tracks which channels the server
+/// registers, and routes its plugin messages to handlers by channel.
+pub struct PluginChannels {
+    handlers: HashMap<String, Handler>,
+    server_channels: BTreeSet<String>,
+    server_brand: Option<String>,
+    pub brand: String,
+}
+
+impl Default for PluginChannels {
+    fn default() -> Self {
+        Self {
+            handlers: HashMap::new(),
+            server_channels: BTreeSet::new(),
+            server_brand: None,
+            brand: "mooshroom".into(),
+        }
+    }
+}
+
+impl PluginChannels {
+    /// Registers `handler` for `channel`, replacing any previous one. A
+    /// returned payload is sent back to the server on the same channel when
+    /// the connection's `Housekeeping::plugin_channels` is on.
+    pub fn register(
+        &mut self,
+        channel: impl Into<String>,
+        handler: impl FnMut(&[u8]) -> Result<Option<Vec<u8>>> + Send + 'static,
+    ) {
+        self.handlers.insert(channel.into(), Box::new(handler));
+    }
+
+    pub fn unregister(&mut self, channel: &str) {
+        self.handlers.remove(channel);
+    }
+
+    /// The channels the server has registered.
+    pub fn server_channels(&self) -> impl Iterator<Item = &str> {
+        self.server_channels.iter().map(String::as_str)
+    }
+
+    pub fn server_brand(&self) -> Option<&str> {
+        self.server_brand.as_deref()
+    }
+
+    /// The messages to send after joining: our brand, then our channels.
+    pub fn announce(&self) -> Result<Vec<ClientPluginMessage>> {
+        let mut brand = Vec::new();
+        self.brand
+            .write_proto::<DEFAULT_PROTOCAL_VERSION>(&mut brand)?;
+        let mut messages = vec![message(BRAND, brand)];
+        if !self.handlers.is_empty() {
+            let mut channels: Vec<&str> = self.handlers.keys().map(String::as_str).collect();
+            channels.sort_unstable();
+            messages.push(message(REGISTER, channels.join("\0").into_bytes()));
+        }
+        Ok(messages)
+    }
+
+    /// Handles a plugin message from the server, returning the reply if its
+    /// handler gave one. A malformed message or failing handler is logged
+    /// and skipped rather than ending the connection.
+    pub fn handle(&mut self, p: &PluginMessage) -> Option<ClientPluginMessage> {
+        let data = &p.data.0;
+        match p.channel.as_str() {
+            REGISTER => self.server_channels.extend(split_channels(data)),
+            UNREGISTER => {
+                for channel in split_channels(data) {
+                    self.server_channels.remove(&channel);
+                }
+            }
+            BRAND => match String::read_proto::<DEFAULT_PROTOCAL_VERSION>(&mut &data[..]) {
+                Ok(brand) => self.server_brand = Some(brand),
+                Err(e) => log::warn!("ignoring malformed server brand: {e}"),
+            },
+            _ => {}
+        }
+        let handler = self.handlers.get_mut(&p.channel)?;
+        match handler(data) {
+            Ok(reply) => reply.map(|data| message(&p.channel, data)),
+            Err(e) => {
+                log::warn!("handler for {} failed: {e}", p.channel);
+                None
+            }
+        }
+    }
+}
+
+fn message(channel: &str, data: Vec<u8>) -> ClientPluginMessage {
+    ClientPluginMessage {
+        channel: channel.into(),
+        data: data.into(),
+    }
+}
+
+fn split_channels(data: &[u8]) -> impl Iterator<Item = String> + '_ {
+    data.split(|b| *b == 0)
+        .filter(|c| !c.is_empty())
+        .map(|c| String::from_utf8_lossy(c).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::error::MooshroomError;
+
+    fn server_message(channel: &str, data: &[u8]) -> PluginMessage {
+        PluginMessage {
+            channel: channel.into(),
+            data: data.to_vec().into(),
+        }
+    }
+
+    #[test]
+    fn test_channel_handshake() {
+        let mut channels = PluginChannels::default();
+        channels.register("example:echo", |data| Ok(Some(data.to_vec())));
+
+        let announce = channels.announce().unwrap();
+        assert_eq!(announce[0].channel, BRAND);
+        assert_eq!(announce[0].data.0, b"\x09mooshroom");
+        assert_eq!(announce[1].data.0, b"example:echo");
+
+        channels.handle(&server_message(REGISTER, b"a:one\0b:two\0"));
+        channels.handle(&server_message(UNREGISTER, b"a:one"));
+        channels.handle(&server_message(BRAND, b"\x05Paper"));
+        assert_eq!(channels.server_channels().collect::<Vec<_>>(), ["b:two"]);
+        assert_eq!(channels.server_brand(), Some("Paper"));
+
+        let reply = channels
+            .handle(&server_message("example:echo", b"hi"))
+            .unwrap();
+        assert_eq!(reply.channel, "example:echo");
+        assert_eq!(reply.data.0, b"hi");
+    }
+
+    #[test]
+    fn test_bad_messages_are_skipped() {
+        let mut channels = PluginChannels::default();
+        channels.register("example:fail", |_| Err(MooshroomError::NotInPlay));
+        assert!(channels
+            .handle(&server_message(BRAND, b"\x7fPaper"))
+            .is_none());
+        assert_eq!(channels.server_brand(), None);
+        assert!(channels
+            .handle(&server_message("example:fail", b"hi"))
+            .is_none());
+    }
+}
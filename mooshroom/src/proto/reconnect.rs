@@ -0,0 +1,220 @@
+use std::{net::TcpStream, time::Duration};
+
+use super::connection::{LoginOptions, MooshroomConnection};
+use crate::core::error::{MooshroomError, Result};
+
+/// Exponential backoff between reconnect attempts.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Backoff {
+    pub initial: Duration,
+    pub max: Duration,
+    pub multiplier: f64,
+    /// Give up after this many failed attempts in a row. `None` retries
+    /// forever.
+    pub max_attempts: Option<u32>,
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self {
+            initial: Duration::from_secs(1),
+            max: Duration::from_secs(60),
+            multiplier: 2.,
+            max_attempts: None,
+        }
+    }
+}
+
+impl Backoff {
+    /// The delay before the given attempt, counting from 1.
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let factor = self.multiplier.powf(attempt.saturating_sub(1) as f64);
+        // Clamp as a float, since the factor outgrows a Duration after a few
+        // dozen attempts.
+        let secs = (self.initial.as_secs_f64() * factor).min(self.max.as_secs_f64());
+        Duration::try_from_secs_f64(secs).unwrap_or(self.max)
+    }
+}
+
+/// Whether an error is worth reconnecting after. Protocol errors would most
+/// likely happen again, so they are returned to the caller instead.
+pub fn is_retryable(e: &MooshroomError) -> bool {
+    matches!(
+        e,
+        MooshroomError::IoError(_)
+            | MooshroomError::ConnectionClosed
+            | MooshroomError::TimedOut
+            | MooshroomError::Disconnected { .. }
+    )
+}
+
+type Connect = Box<dyn FnMut() -> Result<MooshroomConnection>>;
+type BeforeHook = Box<dyn FnMut(&MooshroomError, u32)>;
+type AfterHook = Box<dyn FnMut(&mut MooshroomConnection) -> Result<()>>;
+
+/// Keeps a session running across disconnects and server restarts.
+pub struct ReconnectingClient {
+    connect: Connect,
+    backoff: Backoff,
+    before_reconnect: Vec<BeforeHook>,
+    after_reconnect: Vec<AfterHook>,
+}
+
+impl ReconnectingClient {
+    /// `connect` opens a connection and logs in, returning it in the play
+    /// state.
+    pub fn new(connect: impl FnMut() -> Result<MooshroomConnection> + 'static) -> Self {
+        Self {
+            connect: Box::new(connect),
+            backoff: Backoff::default(),
+            before_reconnect: Vec::new(),
+            after_reconnect: Vec::new(),
+        }
+    }
+
+    /// Connects to `addr` and logs in offline with `options`.
+    pub fn offline(addr: impl Into<String>, options: LoginOptions) -> Self {
+        let addr = addr.into();
+        Self::new(move || {
+            let mut conn =
+                MooshroomConnection::with_options(TcpStream::connect(&addr)?, options.clone());
+            conn.handshake_offline()?;
+            Ok(conn)
+        })
+    }
+
+    pub fn backoff(mut self, backoff: Backoff) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Called with the error and attempt number before waiting to reconnect.
+    pub fn before_reconnect(mut self, hook: impl FnMut(&MooshroomError, u32) + 'static) -> Self {
+        self.before_reconnect.push(Box::new(hook));
+        self
+    }
+
+    /// Called with every new connection, including the first, before it is
+    /// handed to the session.
+    pub fn after_reconnect(
+        mut self,
+        hook: impl FnMut(&mut MooshroomConnection) -> Result<()> + 'static,
+    ) -> Self {
+        self.after_reconnect.push(Box::new(hook));
+        self
+    }
+
+    fn connect(&mut self) -> Result<MooshroomConnection> {
+        let mut conn = (self.connect)()?;
+        for hook in &mut self.after_reconnect {
+            hook(&mut conn)?;
+        }
+        Ok(conn)
+    }
+
+    /// Runs `session` on a connection, reconnecting whenever it fails with a
+    /// [retryable](is_retryable) error. Returns when the session returns
+    /// `Ok`, fails with any other error, or the backoff gives up.
+    pub fn run(
+        &mut self,
+        mut session: impl FnMut(&mut MooshroomConnection) -> Result<()>,
+    ) -> Result<()> {
+        let mut attempt = 0;
+        loop {
+            let result = self.connect().and_then(|mut conn| {
+                attempt = 0;
+                session(&mut conn)
+            });
+            let e = match result {
+                Ok(()) => return Ok(()),
+                Err(e) if !is_retryable(&e) => return Err(e),
+                Err(e) => e,
+            };
+
+            attempt += 1;
+            if self.backoff.max_attempts.is_some_and(|max| attempt > max) {
+                return Err(e);
+            }
+            for hook in &mut self.before_reconnect {
+                hook(&e, attempt);
+            }
+            std::thread::sleep(self.backoff.delay(attempt));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, net::TcpListener, rc::Rc};
+
+    use super::*;
+    use crate::{
+        client::{handshake::Handshake, login::LoginStart},
+        proto::MooshroomProto,
+        server::{
+            login::LoginSuccess,
+            play::{metadata::KeepAlive, player::Disconnect},
+        },
+    };
+
+    #[test]
+    fn test_backoff_delay() {
+        let backoff = Backoff {
+            initial: Duration::from_millis(100),
+            max: Duration::from_millis(500),
+            ..Default::default()
+        };
+        assert_eq!(backoff.delay(1), Duration::from_millis(100));
+        assert_eq!(backoff.delay(3), Duration::from_millis(400));
+        assert_eq!(backoff.delay(10), Duration::from_millis(500));
+        assert_eq!(backoff.delay(1000), Duration::from_millis(500));
+        assert_eq!(backoff.delay(u32::MAX), Duration::from_millis(500));
+    }
+
+    #[test]
+    fn test_reconnect_after_kick() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        std::thread::spawn(move || {
+            for kick in [true, false] {
+                let mut proto = MooshroomProto::new(listener.accept().unwrap().0);
+                let _: Handshake = proto.read_packet().unwrap();
+                let start: LoginStart = proto.read_packet().unwrap();
+                proto
+                    .write_packet(&LoginSuccess {
+                        uuid: uuid::Uuid::new_v4(),
+                        username: start.name,
+                        properties: Vec::new(),
+                    })
+                    .unwrap();
+                if kick {
+                    proto
+                        .write_packet(&Disconnect("Server restarting".into()))
+                        .unwrap();
+                } else {
+                    proto.write_packet(&KeepAlive(1)).unwrap();
+                }
+            }
+        });
+
+        let reasons = Rc::new(RefCell::new(Vec::new()));
+        let seen = reasons.clone();
+        let mut client = ReconnectingClient::offline(addr.to_string(), LoginOptions::default())
+            .backoff(Backoff {
+                initial: Duration::from_millis(10),
+                ..Default::default()
+            })
+            .before_reconnect(move |e, attempt| seen.borrow_mut().push((e.to_string(), attempt)));
+
+        client
+            .run(|conn| {
+                conn.next_play_packet()?;
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(
+            *reasons.borrow(),
+            vec![("Disconnected by server: Server restarting".to_string(), 1)]
+        );
+    }
+}
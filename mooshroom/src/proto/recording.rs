@@ -0,0 +1,389 @@
+use std::{
+    fs::File,
+    io::{self, BufReader, BufWriter, Read, Write},
+    net::{Shutdown, TcpListener},
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use mooshroom_macros::Mooshroom;
+
+use super::{
+    codec::{MooshroomCodec, RawPacket},
+    decode::{Bound, StateTracker},
+    interceptor::{Direction, Intercept, InterceptContext, Interceptor},
+};
+use crate::core::{
+    error::{MooshroomError, Result},
+    io::{MooshroomReadProto, MooshroomWriteProto, DEFAULT_PROTOCAL_VERSION},
+    varint::VarInt,
+};
+
+const PV: usize = DEFAULT_PROTOCAL_VERSION;
+
+/// Starts every recording, followed by the format version.
+pub const MAGIC: &[u8; 4] = b"MSHR";
+pub const FORMAT_VERSION: u8 = 1;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Mooshroom)]
+#[repr(u8)]
+pub enum ProtocolState {
+    #[default]
+    Handshake = 0,
+    Status = 1,
+    Login = 2,
+    Play = 3,
+}
+
+impl ProtocolState {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Handshake => "handshake",
+            Self::Status => "status",
+            Self::Login => "login",
+            Self::Play => "play",
+        }
+    }
+}
+
+/// A recorded packet.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
+    /// Since the recording started.
+    pub time: Duration,
+    pub direction: Direction,
+    pub state: ProtocolState,
+    /// The compression threshold the packet was framed with.
+    pub compression: Option<i32>,
+    pub packet: RawPacket,
+}
+
+/// A frame as stored on disk.
+#[derive(Debug, Clone, Default, Mooshroom)]
+struct StoredFrame {
+    micros: u64,
+    incoming: bool,
+    state: ProtocolState,
+    compression: VarInt,
+    packet_id: VarInt,
+    body: Vec<u8>,
+}
+
+/// Writes frames to a recording.
+pub struct RecordingWriter<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> RecordingWriter<W> {
+    pub fn new(mut writer: W, protocol_version: i32) -> Result<Self> {
+        writer.write_all(MAGIC)?;
+        FORMAT_VERSION.write_proto::<PV>(&mut writer)?;
+        VarInt(protocol_version).write_proto::<PV>(&mut writer)?;
+        Ok(Self { writer })
+    }
+
+    pub fn write_frame(&mut self, frame: &Frame) -> Result<()> {
+        StoredFrame {
+            micros: frame.time.as_micros() as u64,
+            incoming: frame.direction == Direction::Incoming,
+            state: frame.state,
+            compression: VarInt(frame.compression.unwrap_or(-1)),
+            packet_id: frame.packet.id,
+            body: frame.packet.body.to_vec(),
+        }
+        .write_proto::<PV>(&mut self.writer)
+    }
+
+    pub fn flush(&mut self) -> Result<()> {
+        Ok(self.writer.flush()?)
+    }
+}
+
+/// Reads frames from a recording.
+pub struct RecordingReader<R: Read> {
+    reader: R,
+    protocol_version: i32,
+}
+
+impl RecordingReader<BufReader<File>> {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        Self::new(BufReader::new(File::open(path)?))
+    }
+}
+
+impl<R: Read> RecordingReader<R> {
+    pub fn new(mut reader: R) -> Result<Self> {
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(MooshroomError::InvalidRecording("not a recording".into()));
+        }
+        let version = u8::read_proto::<PV>(&mut reader)?;
+        if version != FORMAT_VERSION {
+            return Err(MooshroomError::InvalidRecording(format!(
+                "unsupported format version {version}"
+            )));
+        }
+        let protocol_version = VarInt::read_proto::<PV>(&mut reader)?.0;
+        Ok(Self {
+            reader,
+            protocol_version,
+        })
+    }
+
+    /// The protocol version of the recorded connection.
+    pub fn protocol_version(&self) -> i32 {
+        self.protocol_version
+    }
+
+    /// Returns `None` at the end of the recording.
+    pub fn next_frame(&mut self) -> Result<Option<Frame>> {
+        let mut first = [0];
+        if self.reader.read(&mut first)? == 0 {
+            return Ok(None);
+        }
+        let stored = StoredFrame::read_proto::<PV>(&mut (&first[..]).chain(&mut self.reader))?;
+        Ok(Some(Frame {
+            time: Duration::from_micros(stored.micros),
+            direction: if stored.incoming {
+                Direction::Incoming
+            } else {
+                Direction::Outgoing
+            },
+            state: stored.state,
+            compression: (stored.compression.0 >= 0).then_some(stored.compression.0),
+            packet: RawPacket::new(stored.packet_id, &stored.body[..]),
+        }))
+    }
+}
+
+impl<R: Read> Iterator for RecordingReader<R> {
+    type Item = Result<Frame>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_frame().transpose()
+    }
+}
+
+/// An interceptor that records a client connection. It follows the
+/// connection state and compression from the packets it sees, so it should
+/// be added before the handshake is sent.
+pub struct Recorder {
+    writer: Option<RecordingWriter<Box<dyn Write + Send>>>,
+    start: Instant,
+    tracker: StateTracker,
+    compression: Option<i32>,
+}
+
+impl Recorder {
+    pub fn new(writer: impl Write + Send + 'static) -> Result<Self> {
+        let writer: Box<dyn Write + Send> = Box::new(writer);
+        Ok(Self {
+            writer: Some(RecordingWriter::new(writer, PV as i32)?),
+            start: Instant::now(),
+            tracker: StateTracker::default(),
+            compression: None,
+        })
+    }
+
+    pub fn create(path: impl AsRef<Path>) -> Result<Self> {
+        Self::new(BufWriter::new(File::create(path)?))
+    }
+
+    fn record(&mut self, direction: Direction, packet: &RawPacket) -> Result<()> {
+        let frame = Frame {
+            time: self.start.elapsed(),
+            direction,
+            state: self.tracker.state(),
+            compression: self.compression,
+            packet: packet.clone(),
+        };
+        if let Some(writer) = &mut self.writer {
+            writer.write_frame(&frame)?;
+        }
+
+        let bound = match direction {
+            Direction::Outgoing => Bound::Serverbound,
+            Direction::Incoming => Bound::Clientbound,
+        };
+        if let Some(threshold) = self.tracker.observe(bound, packet)? {
+            self.compression = (threshold >= 0).then_some(threshold);
+        }
+        Ok(())
+    }
+}
+
+impl Interceptor for Recorder {
+    fn on_raw(
+        &mut self,
+        direction: Direction,
+        packet: &RawPacket,
+        _ctx: &mut InterceptContext,
+    ) -> Intercept {
+        if let Err(e) = self.record(direction, packet) {
+            log::error!("Stopped recording. {e}");
+            self.writer = None;
+        }
+        Intercept::Forward
+    }
+}
+
+impl Drop for Recorder {
+    fn drop(&mut self) {
+        if let Some(writer) = &mut self.writer {
+            let _ = writer.flush();
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pacing {
+    /// Packets arrive with the delays they were recorded with.
+    RealTime,
+    AsFastAsPossible,
+}
+
+/// Plays back the packets a recorded client received, framed as they were on
+/// the wire, so it can stand in for the socket of a `MooshroomProto`. What
+/// is written to it is discarded.
+pub struct ReplayStream<R: Read> {
+    recording: RecordingReader<R>,
+    pacing: Pacing,
+    codec: MooshroomCodec<PV>,
+    start: Option<Instant>,
+    buffer: Vec<u8>,
+    position: usize,
+}
+
+impl<R: Read> ReplayStream<R> {
+    pub fn new(recording: RecordingReader<R>, pacing: Pacing) -> Self {
+        Self {
+            recording,
+            pacing,
+            codec: MooshroomCodec::new(),
+            start: None,
+            buffer: Vec::new(),
+            position: 0,
+        }
+    }
+
+    /// Frames the next received packet. Returns `false` at the end.
+    fn next_packet(&mut self) -> Result<bool> {
+        let frame = loop {
+            match self.recording.next_frame()? {
+                Some(f) if f.direction == Direction::Incoming => break f,
+                Some(_) => {}
+                None => return Ok(false),
+            }
+        };
+        let start = *self.start.get_or_insert_with(Instant::now);
+        if self.pacing == Pacing::RealTime {
+            if let Some(wait) = frame.time.checked_sub(start.elapsed()) {
+                std::thread::sleep(wait);
+            }
+        }
+        self.codec.set_compression(frame.compression.unwrap_or(-1));
+        self.buffer = self.codec.encode_raw(&frame.packet)?;
+        self.position = 0;
+        Ok(true)
+    }
+}
+
+impl<R: Read> Read for ReplayStream<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.position == self.buffer.len() {
+            let more = self
+                .next_packet()
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+            if !more {
+                return Ok(0);
+            }
+        }
+        let n = (&self.buffer[self.position..]).read(buf)?;
+        self.position += n;
+        Ok(n)
+    }
+}
+
+impl<R: Read> Write for ReplayStream<R> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Accepts one connection on `listener` and plays the recording back to it
+/// as if it were the server, so a `MooshroomConnection` can connect to it.
+pub fn serve_replay<R: Read>(listener: &TcpListener, replay: ReplayStream<R>) -> Result<()> {
+    let (mut sock, _) = listener.accept()?;
+    let mut incoming = sock.try_clone()?;
+    // Drain what the client sends, so its writes never block.
+    std::thread::spawn(move || io::copy(&mut incoming, &mut io::sink()));
+    let mut replay = replay;
+    io::copy(&mut replay, &mut sock)?;
+    sock.shutdown(Shutdown::Write)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::TcpStream;
+
+    use super::*;
+    use crate::proto::{
+        connection::MooshroomConnection,
+        testing::{spawn_limbo_server, SharedBuffer},
+    };
+
+    fn join_limbo(addr: std::net::SocketAddr, recorder: Option<Recorder>) -> Vec<String> {
+        let mut conn = MooshroomConnection::new(TcpStream::connect(addr).unwrap());
+        if let Some(recorder) = recorder {
+            conn.add_interceptor(recorder);
+        }
+        conn.handshake_offline().unwrap();
+        let mut received = Vec::new();
+        loop {
+            let p = conn.next_play_packet().unwrap();
+            received.push(format!("{p:?}"));
+            if matches!(
+                p,
+                crate::server::play::PlayStage::SynchronizePlayerPosition(_)
+            ) {
+                return received;
+            }
+        }
+    }
+
+    #[test]
+    fn test_record_and_replay() {
+        let addr = spawn_limbo_server();
+        let recording = SharedBuffer::default();
+        let live = join_limbo(addr, Some(Recorder::new(recording.clone()).unwrap()));
+
+        let bytes = recording.bytes();
+        let frames = RecordingReader::new(&bytes[..])
+            .unwrap()
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+        let first = &frames[0];
+        assert_eq!(first.direction, Direction::Outgoing);
+        assert_eq!(first.state, ProtocolState::Handshake);
+        let last = frames.last().unwrap();
+        assert_eq!(last.state, ProtocolState::Play);
+        assert_eq!(last.compression, Some(64));
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let replay_addr = listener.local_addr().unwrap();
+        std::thread::spawn(move || {
+            let recording = RecordingReader::new(io::Cursor::new(bytes)).unwrap();
+            let _ = serve_replay(
+                &listener,
+                ReplayStream::new(recording, Pacing::AsFastAsPossible),
+            );
+        });
+        assert_eq!(join_limbo(replay_addr, None), live);
+    }
+}
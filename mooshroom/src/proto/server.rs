@@ -0,0 +1,596 @@
+use std::{
+    fmt::Debug,
+    io::{Read, Write},
+    net::{Ipv4Addr, Ipv6Addr, SocketAddr, TcpListener, TcpStream, ToSocketAddrs},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver},
+        Arc,
+        Mutex,
+        MutexGuard,
+        PoisonError,
+    },
+    thread::JoinHandle,
+    time::Duration,
+};
+
+use md5::{Digest, Md5};
+
+use super::{
+    auth::GameProfile,
+    codec::RawPacket,
+    reconnect::Backoff,
+    state::{Handshake, Login, Play, Status},
+    status::StatusResponder,
+    MooshroomProto,
+};
+use crate::{
+    client::{
+        self,
+        handshake::{Handshake as HandshakePacket, HandshakeState},
+        login::LoginStart,
+    },
+    containers::Json,
+    core::{
+        data::MooshroomCollection,
+        error::{MooshroomError, Result},
+        io::{MooshroomPacket, DEFAULT_PROTOCAL_VERSION},
+    },
+    server::{
+        login::{Disconnect, LoginSuccess, SetCompression},
+        play::player,
+        status::{PingResponse, StatusBody, StatusResponse},
+    },
+    types::ChatComponent,
+};
+
+/// A protocol state that the client can send packets in.
+pub trait ServerState {
+    type Serverbound: MooshroomCollection<DEFAULT_PROTOCAL_VERSION> + Debug + 'static;
+}
+
+impl ServerState for Status {
+    type Serverbound = client::StatusStage;
+}
+impl ServerState for Login {
+    type Serverbound = client::LoginStage;
+}
+impl ServerState for Play {
+    type Serverbound = client::PlayStage;
+}
+
+/// The server's end of a connection: like [`Connection`](super::state::Connection)
+/// with the directions flipped, reading what the client sends.
+pub struct ServerConnection<S, T = TcpStream> {
+    proto: MooshroomProto<T>,
+    state: S,
+}
+
+/// Where the client asked to go in its handshake.
+pub enum Routed<T = TcpStream> {
+    Status(HandshakePacket, ServerConnection<Status, T>),
+    Login(HandshakePacket, ServerConnection<Login, T>),
+}
+
+impl<S, T> ServerConnection<S, T> {
+    pub fn state(&self) -> &S {
+        &self.state
+    }
+
+    pub fn proto(&self) -> &MooshroomProto<T> {
+        &self.proto
+    }
+
+    pub fn into_proto(self) -> MooshroomProto<T> {
+        self.proto
+    }
+
+    fn transition<N>(self, state: N) -> ServerConnection<N, T> {
+        ServerConnection {
+            proto: self.proto,
+            state,
+        }
+    }
+}
+
+impl<T> ServerConnection<Handshake, T>
+where
+    T: Read,
+{
+    pub fn new(inner: T) -> Self {
+        Self {
+            proto: MooshroomProto::new(inner),
+            state: Handshake,
+        }
+    }
+
+    /// Reads the handshake and moves to the state the client asked for.
+    pub fn route(mut self) -> Result<Routed<T>> {
+        let handshake: HandshakePacket = self.proto.read_packet()?;
+        Ok(match handshake.next_state {
+            HandshakeState::Status => Routed::Status(handshake, self.transition(Status)),
+            HandshakeState::Login => Routed::Login(handshake, self.transition(Login)),
+        })
+    }
+}
+
+impl<S, T> ServerConnection<S, T>
+where
+    S: ServerState,
+    T: Read,
+{
+    pub fn read(&mut self) -> Result<S::Serverbound> {
+        self.proto.read_one_of()
+    }
+
+    /// Like [`read`](Self::read), but returns `None` when the read times out.
+    pub fn try_read(&mut self) -> Result<Option<S::Serverbound>> {
+        self.proto.try_read_one_of()
+    }
+
+    /// Reads the next packet without decoding it, `None` when the read
+    /// times out.
+    pub fn try_read_raw(&mut self) -> Result<Option<RawPacket>> {
+        self.proto.try_read_raw()
+    }
+}
+
+impl<S, T> ServerConnection<S, T>
+where
+    T: Write,
+{
+    pub fn send<P>(&mut self, packet: &P) -> Result<()>
+    where
+        P: MooshroomPacket<DEFAULT_PROTOCAL_VERSION> + Debug + 'static,
+    {
+        self.proto.write_packet(packet)
+    }
+}
+
+impl<T> ServerConnection<Status, T>
+where
+    T: Read + Write,
+{
+    /// Answers the status request with `body` and echoes the ping, after
+    /// which the client closes the connection. Like vanilla, only one request
+    /// is answered; the connection is closed on a second.
+    pub fn serve_status(mut self, body: &StatusBody) -> Result<()> {
+        let mut answered = false;
+        loop {
+            match self.read() {
+                Ok(client::StatusStage::Request(_)) if answered => return Ok(()),
+                Ok(client::StatusStage::Request(_)) => {
+                    self.send(&StatusResponse {
+                        response: Json::new(body.clone()),
+                    })?;
+                    answered = true;
+                }
+                Ok(client::StatusStage::Ping(p)) => return self.send(&PingResponse(p.0)),
+                Err(MooshroomError::ConnectionClosed) => return Ok(()),
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+impl<T> ServerConnection<Login, T>
+where
+    T: Read + Write,
+{
+    pub fn read_login_start(&mut self) -> Result<LoginStart> {
+        self.proto.read_packet()
+    }
+
+    pub fn disconnect(mut self, reason: &str) -> Result<()> {
+        self.send(&Disconnect(chat_json(reason)))
+    }
+
+    /// Enables compression if a threshold is given, then completes the
+    /// login as `profile`.
+    pub fn finish(
+        mut self,
+        profile: GameProfile,
+        compression_threshold: Option<i32>,
+    ) -> Result<ServerConnection<Play, T>> {
+        if let Some(threshold) = compression_threshold {
+            self.send(&SetCompression {
+                threshold: threshold.into(),
+            })?;
+            self.proto.codec.set_compression(threshold);
+        }
+        let state = Play {
+            uuid: profile.uuid,
+            username: profile.name.clone(),
+        };
+        self.send(&LoginSuccess::from(profile))?;
+        Ok(self.transition(state))
+    }
+}
+
+impl<T> ServerConnection<Play, T>
+where
+    T: Write,
+{
+    pub fn disconnect(mut self, reason: &str) -> Result<()> {
+        self.send(&player::Disconnect(chat_json(reason)))
+    }
+}
+
+fn chat_json(text: &str) -> String {
+    serde_json::to_string(&ChatComponent::text(text)).unwrap_or_default()
+}
+
+/// The UUID offline-mode servers give a player, derived from their name.
+pub fn offline_uuid(name: &str) -> uuid::Uuid {
+    let hash = Md5::digest(format!("OfflinePlayer:{name}"));
+    uuid::Builder::from_md5_bytes(hash.into()).into_uuid()
+}
+
+/// Answers server list pings.
+pub trait StatusProvider {
+    fn status(&mut self, handshake: &HandshakePacket) -> StatusBody;
+}
+
+impl<F> StatusProvider for F
+where
+    F: FnMut(&HandshakePacket) -> StatusBody,
+{
+    fn status(&mut self, handshake: &HandshakePacket) -> StatusBody {
+        self(handshake)
+    }
+}
+
+/// Decides who a logging in player is. Returning
+/// [`MooshroomError::Disconnected`] kicks them with its reason.
+pub trait LoginHandler {
+    fn login(&mut self, handshake: &HandshakePacket, start: &LoginStart) -> Result<GameProfile>;
+}
+
+impl<F> LoginHandler for F
+where
+    F: FnMut(&HandshakePacket, &LoginStart) -> Result<GameProfile>,
+{
+    fn login(&mut self, handshake: &HandshakePacket, start: &LoginStart) -> Result<GameProfile> {
+        self(handshake, start)
+    }
+}
+
+/// Accepts every player with their offline-mode UUID.
+pub struct OfflineLogin;
+
+impl LoginHandler for OfflineLogin {
+    fn login(&mut self, _: &HandshakePacket, start: &LoginStart) -> Result<GameProfile> {
+        Ok(GameProfile {
+            uuid: offline_uuid(&start.name),
+            name: start.name.clone(),
+            properties: Vec::new(),
+        })
+    }
+}
+
+type SharedStatus = Arc<Mutex<Box<dyn StatusProvider + Send>>>;
+type SharedLogin = Arc<Mutex<Box<dyn LoginHandler + Send>>>;
+
+/// Accepts connections, answers status pings and logs players in, handing
+/// back connections that reached the play state. Each connection gets its
+/// own thread until then, so a slow or silent client holds up nobody else.
+/// The status provider and login handler are shared by those threads and
+/// called one at a time, so a login handler that does I/O, such as session
+/// authentication, holds up the logins behind it.
+pub struct MooshroomServer {
+    listener: TcpListener,
+    status: SharedStatus,
+    login: SharedLogin,
+    compression_threshold: Option<i32>,
+    timeout: Option<Duration>,
+    /// Connections that reached play, once accepting has started.
+    ready: Option<Receiver<ServerConnection<Play>>>,
+    acceptor: Option<JoinHandle<()>>,
+    closed: Arc<AtomicBool>,
+}
+
+/// How long the accepting thread waits after failing to accept, such as
+/// when out of file descriptors.
+const ACCEPT_BACKOFF: Backoff = Backoff {
+    initial: Duration::from_millis(10),
+    max: Duration::from_secs(1),
+    multiplier: 2.,
+    max_attempts: None,
+};
+
+impl MooshroomServer {
+    pub fn bind(addr: impl ToSocketAddrs) -> Result<Self> {
+        Ok(Self {
+            listener: TcpListener::bind(addr)?,
+            status: Arc::new(Mutex::new(Box::new(StatusResponder::default()))),
+            login: Arc::new(Mutex::new(Box::new(OfflineLogin))),
+            compression_threshold: Some(256),
+            timeout: Some(Duration::from_secs(30)),
+            ready: None,
+            acceptor: None,
+            closed: Default::default(),
+        })
+    }
+
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        Ok(self.listener.local_addr()?)
+    }
+
+    pub fn status(mut self, provider: impl StatusProvider + Send + 'static) -> Self {
+        self.status = Arc::new(Mutex::new(Box::new(provider)));
+        self
+    }
+
+    pub fn login(mut self, handler: impl LoginHandler + Send + 'static) -> Self {
+        self.login = Arc::new(Mutex::new(Box::new(handler)));
+        self
+    }
+
+    /// `None` turns compression off. Defaults to 256, like vanilla.
+    pub fn compression_threshold(mut self, threshold: Option<i32>) -> Self {
+        self.compression_threshold = threshold;
+        self
+    }
+
+    /// How long a client may stay silent before reaching the play state.
+    pub fn timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Waits for the next connection to reach the play state. Status pings,
+    /// rejected logins and clients that misbehave before play are dealt with
+    /// along the way, on their own threads.
+    pub fn accept(&mut self) -> Result<ServerConnection<Play>> {
+        if self.ready.is_none() {
+            let (acceptor, ready) = self.start_accepting()?;
+            self.acceptor = Some(acceptor);
+            self.ready = Some(ready);
+        }
+        self.ready
+            .as_ref()
+            .and_then(|ready| ready.recv().ok())
+            .ok_or(MooshroomError::ConnectionClosed)
+    }
+
+    /// Starts a thread that accepts connections and hands each to a thread
+    /// of its own, which sends it back over the channel if it logs in.
+    fn start_accepting(&self) -> Result<(JoinHandle<()>, Receiver<ServerConnection<Play>>)> {
+        let listener = self.listener.try_clone()?;
+        let handshaker = self.handshaker();
+        let timeout = self.timeout;
+        let closed = self.closed.clone();
+        let (tx, rx) = mpsc::channel();
+        let acceptor = std::thread::spawn(move || {
+            let mut failures = 0;
+            loop {
+                let accepted = listener.accept();
+                if closed.load(Ordering::Relaxed) {
+                    break;
+                }
+                let (stream, addr) = match accepted {
+                    Ok(accepted) => accepted,
+                    Err(e) => {
+                        failures += 1;
+                        log::warn!("failed to accept a connection: {e}");
+                        std::thread::sleep(ACCEPT_BACKOFF.delay(failures));
+                        continue;
+                    }
+                };
+                failures = 0;
+                let handshaker = handshaker.clone();
+                let tx = tx.clone();
+                std::thread::spawn(move || match handshaker.accept(stream, timeout) {
+                    Ok(Some(conn)) => {
+                        let _ = tx.send(conn);
+                    }
+                    Ok(None) => {}
+                    Err(e) => log::debug!("connection from {addr} failed: {e}"),
+                });
+            }
+        });
+        Ok((acceptor, rx))
+    }
+
+    fn handshaker(&self) -> Handshaker {
+        Handshaker {
+            status: self.status.clone(),
+            login: self.login.clone(),
+            compression_threshold: self.compression_threshold,
+        }
+    }
+
+    /// Runs one connection through the handshake, returning it if it logged
+    /// in, or `None` if it was a status ping or the login was rejected.
+    pub fn handle<T: Read + Write>(&self, stream: T) -> Result<Option<ServerConnection<Play, T>>> {
+        self.handshaker().handle(stream)
+    }
+}
+
+impl Drop for MooshroomServer {
+    fn drop(&mut self) {
+        let acceptor = match self.acceptor.take() {
+            Some(acceptor) => acceptor,
+            None => return,
+        };
+        self.closed.store(true, Ordering::Relaxed);
+        // Wake the accepting thread, which checks the flag once accept
+        // returns, so it releases the port.
+        let woken = self.listener.local_addr().and_then(|mut addr| {
+            if addr.ip().is_unspecified() {
+                addr.set_ip(match addr {
+                    SocketAddr::V4(_) => Ipv4Addr::LOCALHOST.into(),
+                    SocketAddr::V6(_) => Ipv6Addr::LOCALHOST.into(),
+                });
+            }
+            TcpStream::connect(addr)
+        });
+        if woken.is_ok() {
+            let _ = acceptor.join();
+        }
+    }
+}
+
+/// Locks a handler shared by the connection threads. A handler that panicked
+/// on one connection is still used for the next.
+fn lock<T: ?Sized>(handler: &Mutex<T>) -> MutexGuard<'_, T> {
+    handler.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// What a connection's thread needs to get it to the play state.
+#[derive(Clone)]
+struct Handshaker {
+    status: SharedStatus,
+    login: SharedLogin,
+    compression_threshold: Option<i32>,
+}
+
+impl Handshaker {
+    fn accept(
+        &self,
+        stream: TcpStream,
+        timeout: Option<Duration>,
+    ) -> Result<Option<ServerConnection<Play>>> {
+        stream.set_read_timeout(timeout)?;
+        let conn = self.handle(stream)?;
+        if let Some(conn) = &conn {
+            conn.proto.set_read_timeout(None)?;
+        }
+        Ok(conn)
+    }
+
+    fn handle<T: Read + Write>(&self, stream: T) -> Result<Option<ServerConnection<Play, T>>> {
+        match ServerConnection::new(stream).route()? {
+            Routed::Status(handshake, conn) => {
+                let body = lock(&self.status).status(&handshake);
+                conn.serve_status(&body)?;
+                Ok(None)
+            }
+            Routed::Login(handshake, mut conn) => {
+                let start = conn.read_login_start()?;
+                let profile = lock(&self.login).login(&handshake, &start);
+                match profile {
+                    Ok(profile) => Ok(Some(conn.finish(profile, self.compression_threshold)?)),
+                    Err(MooshroomError::Disconnected { reason }) => {
+                        conn.disconnect(&reason)?;
+                        Ok(None)
+                    }
+                    Err(e) => Err(e),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proto::{
+        connection::{LoginOptions, MooshroomConnection},
+        ping::ping_server,
+    };
+
+    #[test]
+    fn test_offline_uuid() {
+        assert_eq!(
+            offline_uuid("Notch").to_string(),
+            "b50ad385-829d-3141-a216-7e7d7539ba7f"
+        );
+    }
+
+    #[test]
+    fn test_status_then_login() {
+        let mut server = MooshroomServer::bind("127.0.0.1:0").unwrap().login(
+            |_: &HandshakePacket, start: &LoginStart| {
+                if start.name == "Herobrine" {
+                    return Err(MooshroomError::Disconnected {
+                        reason: "Not today".into(),
+                    });
+                }
+                OfflineLogin.login(&HandshakePacket::default(), start)
+            },
+        );
+        let addr = server.local_addr().unwrap();
+
+        let server = std::thread::spawn(move || {
+            let mut conn = server.accept().unwrap();
+            let message = match conn.read().unwrap() {
+                client::PlayStage::ChatMessage(p) => p.message,
+                p => panic!("unexpected {p:?}"),
+            };
+            (conn.state().username.clone(), conn.state().uuid, message)
+        });
+
+        let ping = ping_server(&addr.to_string()).unwrap();
+        assert_eq!(ping.status.description.to_plain(), "A Mooshroom server");
+
+        let login = |name: &str| {
+            let stream = TcpStream::connect(addr).unwrap();
+            let mut conn = MooshroomConnection::with_options(stream, LoginOptions::new(name));
+            conn.handshake_offline().map(|_| conn)
+        };
+        let err = login("Herobrine").err().unwrap();
+        assert!(
+            matches!(err, MooshroomError::Disconnected { reason } if reason.contains("Not today"))
+        );
+        login("Steve").unwrap().send_chat("hello").unwrap();
+
+        let (username, uuid, message) = server.join().unwrap();
+        assert_eq!(username, "Steve");
+        assert_eq!(uuid, offline_uuid("Steve"));
+        assert_eq!(message, "hello");
+    }
+
+    #[test]
+    fn test_silent_client_does_not_block() {
+        let mut server = MooshroomServer::bind("127.0.0.1:0").unwrap();
+        let addr = server.local_addr().unwrap();
+        let _silent = TcpStream::connect(addr).unwrap();
+
+        let server = std::thread::spawn(move || server.accept().unwrap().state().username.clone());
+        let stream = TcpStream::connect(addr).unwrap();
+        stream
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        let mut conn = MooshroomConnection::with_options(stream, LoginOptions::new("Steve"));
+        conn.handshake_offline().unwrap();
+        assert_eq!(server.join().unwrap(), "Steve");
+    }
+
+    #[test]
+    fn test_drop_releases_the_port() {
+        let mut server = MooshroomServer::bind("127.0.0.1:0").unwrap();
+        let addr = server.local_addr().unwrap();
+        let client = std::thread::spawn(move || {
+            let stream = TcpStream::connect(addr).unwrap();
+            let mut conn = MooshroomConnection::with_options(stream, LoginOptions::new("Steve"));
+            conn.handshake_offline().unwrap();
+        });
+        server.accept().unwrap();
+        client.join().unwrap();
+        drop(server);
+        TcpListener::bind(addr).unwrap();
+    }
+
+    #[test]
+    fn test_panicking_handler_does_not_poison() {
+        let mut server = MooshroomServer::bind("127.0.0.1:0").unwrap().login(
+            |_: &HandshakePacket, start: &LoginStart| {
+                if start.name == "Herobrine" {
+                    panic!("no");
+                }
+                OfflineLogin.login(&HandshakePacket::default(), start)
+            },
+        );
+        let addr = server.local_addr().unwrap();
+        let server = std::thread::spawn(move || server.accept().unwrap().state().username.clone());
+        let login = |name: &str| {
+            let stream = TcpStream::connect(addr).unwrap();
+            let mut conn = MooshroomConnection::with_options(stream, LoginOptions::new(name));
+            conn.handshake_offline().map(|_| conn)
+        };
+        assert!(login("Herobrine").is_err());
+        login("Steve").unwrap();
+        assert_eq!(server.join().unwrap(), "Steve");
+    }
+}
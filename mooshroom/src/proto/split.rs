@@ -0,0 +1,282 @@
+use std::{
+    any::Any,
+    collections::VecDeque,
+    fmt::Debug,
+    io::{Read, Write},
+    net::TcpStream,
+    time::Duration,
+};
+
+use bytes::BytesMut;
+
+use super::{codec::RawPacket, MooshroomProto};
+use crate::{
+    core::{
+        data::MooshroomCollection,
+        error::Result,
+        io::{MooshroomPacket, MooshroomReadProto, DEFAULT_PROTOCAL_VERSION},
+        varint::VarInt,
+    },
+    server::login::{LoginStage, LoginSuccess, SetCompression},
+};
+
+const SET_COMPRESSION_ID: VarInt =
+    <SetCompression as MooshroomPacket<DEFAULT_PROTOCAL_VERSION>>::PACKET_ID;
+const LOGIN_SUCCESS_ID: VarInt =
+    <LoginSuccess as MooshroomPacket<DEFAULT_PROTOCAL_VERSION>>::PACKET_ID;
+
+/// The reading half of a split [`MooshroomProto`].
+///
+/// Compression changes read by this half are applied to the writer too.
+pub struct MooshroomReader<T> {
+    proto: MooshroomProto<T>,
+    /// Set once a login success is read, after which no compression change
+    /// can follow.
+    logged_in: bool,
+}
+
+/// The writing half of a split [`MooshroomProto`].
+pub struct MooshroomWriter<T> {
+    proto: MooshroomProto<T>,
+}
+
+impl<T> MooshroomProto<T> {
+    /// Splits the connection into halves that can be moved to separate
+    /// threads. `writer` must write to the same connection as this proto.
+    pub fn split_with<W>(self, writer: W) -> (MooshroomReader<T>, MooshroomWriter<W>) {
+        let (rx_codec, tx_codec) = self.codec.split();
+        let reader = MooshroomProto {
+            inner: self.inner,
+            codec: rx_codec,
+            tx_buffer: BytesMut::new(),
+            nonblocking: self.nonblocking,
+            hooks: self.hooks.clone(),
+            injected: self.injected,
+            after_read: None,
+        };
+        let writer = MooshroomProto {
+            inner: writer,
+            codec: tx_codec,
+            tx_buffer: self.tx_buffer,
+            nonblocking: self.nonblocking,
+            hooks: self.hooks,
+            injected: VecDeque::new(),
+            after_read: None,
+        };
+        (
+            MooshroomReader {
+                proto: reader,
+                logged_in: false,
+            },
+            MooshroomWriter { proto: writer },
+        )
+    }
+}
+
+impl MooshroomProto<TcpStream> {
+    pub fn split(self) -> Result<(MooshroomReader<TcpStream>, MooshroomWriter<TcpStream>)> {
+        let writer = self.inner.try_clone()?;
+        Ok(self.split_with(writer))
+    }
+}
+
+impl<T> MooshroomReader<T> {
+    pub fn proto(&self) -> &MooshroomProto<T> {
+        &self.proto
+    }
+
+    fn observe(&mut self, packet: &dyn Any) {
+        let threshold = if let Some(LoginStage::SetCompression(p)) = packet.downcast_ref() {
+            p.threshold.0
+        } else if let Some(p) = packet.downcast_ref::<SetCompression>() {
+            p.threshold.0
+        } else {
+            if let Some(LoginStage::Success(_)) = packet.downcast_ref() {
+                self.logged_in = true;
+            }
+            self.logged_in |= packet.is::<LoginSuccess>();
+            return;
+        };
+        self.proto.codec.set_compression(threshold);
+    }
+
+    /// Like [`observe`](Self::observe) without knowing the state. Until a
+    /// login success, a packet that reads exactly as a set compression is
+    /// taken as one; no play packet with its id is that short.
+    fn observe_raw(&mut self, raw: &RawPacket) {
+        if self.logged_in {
+            return;
+        }
+        if raw.id == LOGIN_SUCCESS_ID {
+            self.logged_in = true;
+        } else if raw.id == SET_COMPRESSION_ID {
+            let mut body = raw.body.as_ref();
+            if let Ok(threshold) = VarInt::read_proto::<DEFAULT_PROTOCAL_VERSION>(&mut body) {
+                if body.is_empty() {
+                    self.proto.codec.set_compression(threshold.0);
+                }
+            }
+        }
+    }
+}
+
+impl MooshroomReader<TcpStream> {
+    pub fn set_read_timeout(&self, timeout: Option<Duration>) -> Result<()> {
+        self.proto.set_read_timeout(timeout)
+    }
+}
+
+impl<T> MooshroomReader<T>
+where
+    T: Read,
+{
+    pub fn read_packet<P>(&mut self) -> Result<P>
+    where
+        P: MooshroomPacket<DEFAULT_PROTOCAL_VERSION> + Debug + 'static,
+    {
+        let p = self.proto.read_packet()?;
+        self.observe(&p);
+        Ok(p)
+    }
+
+    pub fn read_one_of<P>(&mut self) -> Result<P>
+    where
+        P: MooshroomCollection<DEFAULT_PROTOCAL_VERSION> + Debug + 'static,
+    {
+        let p = self.proto.read_one_of()?;
+        self.observe(&p);
+        Ok(p)
+    }
+
+    pub fn read_raw(&mut self) -> Result<RawPacket> {
+        let raw = self.proto.read_raw()?;
+        self.observe_raw(&raw);
+        Ok(raw)
+    }
+
+    pub fn try_read_packet<P>(&mut self) -> Result<Option<P>>
+    where
+        P: MooshroomPacket<DEFAULT_PROTOCAL_VERSION> + Debug + 'static,
+    {
+        let p = self.proto.try_read_packet()?;
+        if let Some(p) = &p {
+            self.observe(p);
+        }
+        Ok(p)
+    }
+
+    pub fn try_read_one_of<P>(&mut self) -> Result<Option<P>>
+    where
+        P: MooshroomCollection<DEFAULT_PROTOCAL_VERSION> + Debug + 'static,
+    {
+        let p = self.proto.try_read_one_of()?;
+        if let Some(p) = &p {
+            self.observe(p);
+        }
+        Ok(p)
+    }
+}
+
+impl<T> MooshroomWriter<T> {
+    pub fn proto(&self) -> &MooshroomProto<T> {
+        &self.proto
+    }
+
+    pub fn pending_write(&self) -> usize {
+        self.proto.pending_write()
+    }
+}
+
+impl MooshroomWriter<TcpStream> {
+    pub fn set_write_timeout(&self, timeout: Option<Duration>) -> Result<()> {
+        self.proto.set_write_timeout(timeout)
+    }
+}
+
+impl<T> MooshroomWriter<T>
+where
+    T: Write,
+{
+    pub fn write_packet<P>(&mut self, p: &P) -> Result<()>
+    where
+        P: MooshroomPacket<DEFAULT_PROTOCAL_VERSION> + Debug + 'static,
+    {
+        self.proto.write_packet(p)
+    }
+
+    pub fn write_raw(&mut self, p: &RawPacket) -> Result<()> {
+        self.proto.write_raw(p)
+    }
+
+    pub fn flush(&mut self) -> Result<bool> {
+        self.proto.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::TcpListener;
+
+    use super::*;
+    use crate::{
+        client::metadata::KeepAliveResponse,
+        proto::{codec::MooshroomCodec, testing::MockStream},
+        server::play::metadata::KeepAlive,
+    };
+
+    const PV: usize = DEFAULT_PROTOCAL_VERSION;
+
+    #[test]
+    fn test_split_shares_compression() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let stream = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (mut server, _) = listener.accept().unwrap();
+
+        let mut server_codec = MooshroomCodec::<PV>::new();
+        let mut tx = server_codec
+            .encode(&SetCompression {
+                threshold: 0.into(),
+            })
+            .unwrap();
+        server_codec.set_compression(0);
+        tx.extend(server_codec.encode(&KeepAlive(7)).unwrap());
+        server.write_all(&tx).unwrap();
+
+        let (mut reader, mut writer) = MooshroomProto::new(stream).split().unwrap();
+        let handle = std::thread::spawn(move || {
+            let stage: LoginStage = reader.read_one_of().unwrap();
+            assert!(matches!(stage, LoginStage::SetCompression(_)));
+            reader.read_packet::<KeepAlive>().unwrap().0
+        });
+        let id = handle.join().unwrap();
+        assert_eq!(id, 7);
+
+        writer.write_packet(&KeepAliveResponse(id)).unwrap();
+        let mut buffer = [0; 64];
+        let n = server.read(&mut buffer).unwrap();
+        server_codec.add_bytes(&buffer[..n]);
+        let p: KeepAliveResponse = server_codec.read_packet().unwrap().unwrap();
+        assert_eq!(p.0, 7);
+    }
+
+    #[test]
+    fn test_raw_reads_share_compression() {
+        let mut server_codec = MooshroomCodec::<PV>::new();
+        let mut rx = server_codec
+            .encode(&SetCompression {
+                threshold: 0.into(),
+            })
+            .unwrap();
+        server_codec.set_compression(0);
+        rx.extend(server_codec.encode(&LoginSuccess::default()).unwrap());
+        rx.extend(server_codec.encode(&KeepAlive(7)).unwrap());
+
+        let (mut reader, writer) =
+            MooshroomProto::new(MockStream::new(rx)).split_with(Vec::<u8>::new());
+        assert_eq!(reader.read_raw().unwrap().id, SET_COMPRESSION_ID);
+        assert_eq!(writer.proto().codec.compression(), Some(0));
+        assert_eq!(reader.read_raw().unwrap().id, LOGIN_SUCCESS_ID);
+        let raw = reader.read_raw().unwrap();
+        assert_eq!(raw.decode::<PV, KeepAlive>().unwrap().0, 7);
+    }
+}
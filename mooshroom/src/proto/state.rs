@@ -0,0 +1,369 @@
+use std::{
+    fmt::Debug,
+    io::{Read, Write},
+    net::{TcpStream, ToSocketAddrs},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use super::MooshroomProto;
+use crate::{
+    client::{
+        chat,
+        handshake::{Handshake as HandshakePacket, HandshakeState},
+        inventory,
+        login::{EncryptionResponse, LoginPluginResponse, LoginStart},
+        metadata::{self, KeepAliveResponse},
+        movement,
+        player,
+        status::{PingRequest, StatusRequest},
+        world,
+    },
+    core::{
+        data::MooshroomCollection,
+        error::{MooshroomError, Result},
+        io::{MooshroomPacket, DEFAULT_PROTOCAL_VERSION},
+    },
+    server::{
+        login::{Disconnect, LoginStage, LoginSuccess, SetCompression},
+        play::PlayStage,
+        status::{StatusResponse, StatusStage},
+    },
+};
+
+pub struct Handshake;
+pub struct Status;
+pub struct Login;
+pub struct Play {
+    pub uuid: uuid::Uuid,
+    pub username: String,
+}
+
+/// A protocol state that the server can send packets in.
+pub trait ConnectionState {
+    type Clientbound: MooshroomCollection<DEFAULT_PROTOCAL_VERSION> + Debug + 'static;
+}
+
+/// Marks a packet as valid for the client to send while in state `S`.
+pub trait Serverbound<S>: MooshroomPacket<DEFAULT_PROTOCAL_VERSION> + Debug + 'static {}
+
+macro_rules! serverbound {
+    ($state:ty => $($packet:ty),+ $(,)?) => {
+        $( impl Serverbound<$state> for $packet {} )+
+    };
+}
+
+impl ConnectionState for Status {
+    type Clientbound = StatusStage;
+}
+impl ConnectionState for Login {
+    type Clientbound = LoginStage;
+}
+impl ConnectionState for Play {
+    type Clientbound = PlayStage;
+}
+
+serverbound!(Status => StatusRequest, PingRequest);
+serverbound!(Login => LoginStart, EncryptionResponse, LoginPluginResponse);
+serverbound!(
+    Play => KeepAliveResponse,
+    player::Action,
+    movement::SetPlayerPosition,
+    movement::SetPlayerPositionAndRotation,
+    movement::SetPlayerRotation,
+    movement::SetPlayerOnGround,
+    movement::MoveVehicle,
+    movement::PaddleBoat,
+    movement::PlayerCommand,
+    inventory::ClickContainerButton,
+    inventory::ClickContainer,
+    inventory::CloseContainer,
+    inventory::PickItem,
+    inventory::SetHeldItem,
+    inventory::SetCreativeModeSlot,
+    chat::MessageAcknowledgment,
+    chat::ChatCommand,
+    chat::ChatMessage,
+    chat::ChatPreview,
+    world::Interact,
+    world::PlayerAction,
+    world::UpdateSign,
+    world::SwingArm,
+    world::UseItemOn,
+    world::UseItem,
+    metadata::ClientInformation,
+    metadata::PluginMessage,
+);
+
+/// A client connection that tracks the protocol state in its type, so only
+/// packets valid for the current state can be read or sent.
+///
+/// ```no_run
+/// use mooshroom::{client::login::LoginStart, proto::state::Connection};
+///
+/// # fn main() -> mooshroom::core::error::Result<()> {
+/// let mut play = Connection::connect("localhost:25565")?
+///     .login("localhost", 25565)?
+///     .login_offline(&LoginStart::default())?;
+/// let packet = play.read()?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// Using a connection in the wrong state does not compile. A status
+/// connection can't log in:
+///
+/// ```compile_fail
+/// use mooshroom::{client::login::LoginStart, proto::state::Connection};
+///
+/// # fn main() -> mooshroom::core::error::Result<()> {
+/// let status = Connection::connect("localhost:25565")?.status("localhost", 25565)?;
+/// status.login_offline(&LoginStart::default())?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// Nothing can be read before the handshake:
+///
+/// ```compile_fail
+/// use mooshroom::proto::state::Connection;
+///
+/// # fn main() -> mooshroom::core::error::Result<()> {
+/// let mut conn = Connection::connect("localhost:25565")?;
+/// conn.read()?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// And play packets can't be sent while logging in:
+///
+/// ```compile_fail
+/// use mooshroom::{client::metadata::KeepAliveResponse, proto::state::Connection};
+///
+/// # fn main() -> mooshroom::core::error::Result<()> {
+/// let mut login = Connection::connect("localhost:25565")?.login("localhost", 25565)?;
+/// login.send(&KeepAliveResponse(0))?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct Connection<S, T = TcpStream> {
+    proto: MooshroomProto<T>,
+    state: S,
+}
+
+impl<S, T> Connection<S, T> {
+    pub fn state(&self) -> &S {
+        &self.state
+    }
+
+    pub fn proto(&self) -> &MooshroomProto<T> {
+        &self.proto
+    }
+
+    pub fn into_proto(self) -> MooshroomProto<T> {
+        self.proto
+    }
+
+    fn transition<N>(self, state: N) -> Connection<N, T> {
+        Connection {
+            proto: self.proto,
+            state,
+        }
+    }
+}
+
+impl Connection<Handshake> {
+    pub fn connect(addr: impl ToSocketAddrs) -> Result<Self> {
+        Ok(Self::new(TcpStream::connect(addr)?))
+    }
+}
+
+impl<T> Connection<Handshake, T> {
+    pub fn new(inner: T) -> Self {
+        Self {
+            proto: MooshroomProto::new(inner),
+            state: Handshake,
+        }
+    }
+}
+
+impl<T> Connection<Handshake, T>
+where
+    T: Write,
+{
+    pub fn status(
+        self,
+        server_address: impl Into<String>,
+        server_port: u16,
+    ) -> Result<Connection<Status, T>> {
+        self.handshake(server_address.into(), server_port, HandshakeState::Status)
+            .map(|c| c.transition(Status))
+    }
+
+    pub fn login(
+        self,
+        server_address: impl Into<String>,
+        server_port: u16,
+    ) -> Result<Connection<Login, T>> {
+        self.handshake(server_address.into(), server_port, HandshakeState::Login)
+            .map(|c| c.transition(Login))
+    }
+
+    fn handshake(
+        mut self,
+        server_address: String,
+        server_port: u16,
+        next_state: HandshakeState,
+    ) -> Result<Self> {
+        let protocol_version = self.proto.protocal_version().into();
+        self.proto.write_packet(&HandshakePacket {
+            protocol_version,
+            server_address,
+            server_port,
+            next_state,
+        })?;
+        Ok(self)
+    }
+}
+
+impl<S, T> Connection<S, T>
+where
+    S: ConnectionState,
+    T: Read,
+{
+    pub fn read(&mut self) -> Result<S::Clientbound> {
+        self.proto.read_one_of()
+    }
+}
+
+impl<S, T> Connection<S, T>
+where
+    T: Write,
+{
+    pub fn send<P: Serverbound<S>>(&mut self, packet: &P) -> Result<()> {
+        self.proto.write_packet(packet)
+    }
+}
+
+impl<T> Connection<Status, T>
+where
+    T: Read + Write,
+{
+    pub fn request_status(&mut self) -> Result<StatusResponse> {
+        self.proto.send_command(&StatusRequest)
+    }
+
+    /// Sends a ping and waits for the matching pong, returning the round
+    /// trip time.
+    pub fn ping(&mut self) -> Result<Duration> {
+        let start = Instant::now();
+        let payload = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64;
+        let pong = self.proto.send_command(&PingRequest(payload))?;
+        if pong.0 != payload {
+            return Err(MooshroomError::PongMismatch {
+                sent: payload,
+                received: pong.0,
+            });
+        }
+        Ok(start.elapsed())
+    }
+}
+
+impl<T> Connection<Login, T> {
+    pub fn set_compression(mut self, packet: &SetCompression) -> Self {
+        self.proto.codec.set_compression(packet.threshold.0);
+        self
+    }
+
+    pub fn success(self, packet: LoginSuccess) -> Connection<Play, T> {
+        self.transition(Play {
+            uuid: packet.uuid,
+            username: packet.username,
+        })
+    }
+}
+
+impl<T> Connection<Login, T>
+where
+    T: Read + Write,
+{
+    pub fn login_offline(mut self, start: &LoginStart) -> Result<Connection<Play, T>> {
+        self.send(start)?;
+        loop {
+            match self.read()? {
+                LoginStage::SetCompression(p) => self = self.set_compression(&p),
+                LoginStage::Success(p) => return Ok(self.success(p)),
+                LoginStage::LoginPluginRequest(p) => self.send(&LoginPluginResponse {
+                    message_id: p.message_id,
+                    data: None,
+                })?,
+                LoginStage::EncryptionRequest(_) => {
+                    return Err(MooshroomError::AuthenticationFailed(
+                        "server is in online mode".into(),
+                    ))
+                }
+                LoginStage::Disconnect(Disconnect(reason)) => {
+                    return Err(MooshroomError::Disconnected { reason })
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        proto::{codec::MooshroomCodec, testing::MockStream},
+        server::status::PingResponse,
+    };
+
+    #[test]
+    fn test_login_transitions_to_play() {
+        let uuid = uuid::Uuid::new_v4();
+        let mut server = MooshroomCodec::<DEFAULT_PROTOCAL_VERSION>::new();
+        let mut rx = server
+            .encode(&SetCompression {
+                threshold: 16.into(),
+            })
+            .unwrap();
+        server.set_compression(16);
+        rx.extend(
+            server
+                .encode(&LoginSuccess {
+                    uuid,
+                    username: "mooshroom".into(),
+                    properties: Vec::new(),
+                })
+                .unwrap(),
+        );
+
+        let conn = Connection::new(MockStream::new(rx));
+        let play = conn
+            .login("localhost", 25565)
+            .unwrap()
+            .login_offline(&LoginStart {
+                name: "mooshroom".into(),
+                ..Default::default()
+            })
+            .unwrap();
+
+        assert_eq!(play.state().uuid, uuid);
+        assert_eq!(play.state().username, "mooshroom");
+    }
+
+    #[test]
+    fn test_ping_checks_the_payload() {
+        let mut server = MooshroomCodec::<DEFAULT_PROTOCAL_VERSION>::new();
+        let rx = server.encode(&PingResponse(0)).unwrap();
+        let mut conn = Connection::new(MockStream::new(rx))
+            .status("localhost", 25565)
+            .unwrap();
+        assert!(matches!(
+            conn.ping(),
+            Err(MooshroomError::PongMismatch { received: 0, .. })
+        ));
+    }
+}
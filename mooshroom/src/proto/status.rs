@@ -0,0 +1,183 @@
+use std::{net::ToSocketAddrs, path::Path};
+
+use super::server::{MooshroomServer, StatusProvider};
+use crate::{
+    client::{handshake::Handshake, login::LoginStart},
+    core::{
+        error::{MooshroomError, Result},
+        io::DEFAULT_PROTOCAL_VERSION,
+    },
+    server::status::{ServerPlayer, ServerPlayers, ServerVersion, StatusBody},
+    types::ChatComponent,
+};
+
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+/// The size the client expects server icons to be.
+pub const FAVICON_SIZE: u32 = 64;
+
+/// Answers server list pings with a configured status.
+#[derive(Debug, Clone)]
+pub struct StatusResponder {
+    body: StatusBody,
+    echo_protocol: bool,
+    kick_message: String,
+}
+
+impl Default for StatusResponder {
+    fn default() -> Self {
+        Self {
+            body: StatusBody {
+                version: ServerVersion {
+                    name: "1.19.2".into(),
+                    protocol: DEFAULT_PROTOCAL_VERSION as i32,
+                },
+                description: ChatComponent::text("A Mooshroom server"),
+                ..Default::default()
+            },
+            echo_protocol: false,
+            kick_message: "This server is not accepting players".into(),
+        }
+    }
+}
+
+impl StatusResponder {
+    pub fn motd(mut self, motd: ChatComponent) -> Self {
+        self.body.description = motd;
+        self
+    }
+
+    pub fn version(mut self, name: impl Into<String>, protocol: i32) -> Self {
+        self.body.version = ServerVersion {
+            name: name.into(),
+            protocol,
+        };
+        self
+    }
+
+    /// Reports the protocol the client pinged with as ours, so every client
+    /// shows the server as compatible.
+    pub fn echo_protocol(mut self, echo: bool) -> Self {
+        self.echo_protocol = echo;
+        self
+    }
+
+    pub fn players(mut self, online: usize, max: usize) -> Self {
+        self.body.players.online = online;
+        self.body.players.max = max;
+        self
+    }
+
+    /// The names shown when hovering the player count.
+    pub fn sample(mut self, sample: Vec<ServerPlayer>) -> Self {
+        self.body.players.sample = sample;
+        self
+    }
+
+    /// Sets the icon from PNG data, which must be 64x64.
+    pub fn favicon_png(mut self, png: &[u8]) -> Result<Self> {
+        let (width, height) = png_size(png)?;
+        if (width, height) != (FAVICON_SIZE, FAVICON_SIZE) {
+            return Err(MooshroomError::InvalidFavicon(format!(
+                "expected {FAVICON_SIZE}x{FAVICON_SIZE}, got {width}x{height}"
+            )));
+        }
+        self.body.set_favicon_png(png);
+        Ok(self)
+    }
+
+    pub fn favicon_file(self, path: impl AsRef<Path>) -> Result<Self> {
+        let png = std::fs::read(path)?;
+        self.favicon_png(&png)
+    }
+
+    /// The message players trying to join are kicked with by [`serve`](Self::serve).
+    pub fn kick_message(mut self, message: impl Into<String>) -> Self {
+        self.kick_message = message.into();
+        self
+    }
+
+    pub fn players_mut(&mut self) -> &mut ServerPlayers {
+        &mut self.body.players
+    }
+
+    /// Runs a status-only server on `addr`, such as for a maintenance page.
+    /// Only returns if accepting connections stops.
+    pub fn serve(self, addr: impl ToSocketAddrs) -> Result<()> {
+        let reason = self.kick_message.clone();
+        let mut server = MooshroomServer::bind(addr)?.status(self).login(
+            move |_: &Handshake, _: &LoginStart| {
+                Err(MooshroomError::Disconnected {
+                    reason: reason.clone(),
+                })
+            },
+        );
+        loop {
+            server.accept()?;
+        }
+    }
+}
+
+impl StatusProvider for StatusResponder {
+    fn status(&mut self, handshake: &Handshake) -> StatusBody {
+        let mut body = self.body.clone();
+        if self.echo_protocol {
+            body.version.protocol = handshake.protocol_version.0;
+        }
+        body
+    }
+}
+
+/// Reads the image size from the IHDR chunk, which comes first.
+fn png_size(png: &[u8]) -> Result<(u32, u32)> {
+    let invalid = || MooshroomError::InvalidFavicon("not a png".into());
+    if !png.starts_with(PNG_SIGNATURE) || png.get(12..16) != Some(b"IHDR") {
+        return Err(invalid());
+    }
+    let dimension = |at: usize| {
+        png.get(at..at + 4)
+            .map(|b| u32::from_be_bytes(b.try_into().unwrap()))
+            .ok_or_else(invalid)
+    };
+    Ok((dimension(16)?, dimension(20)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn png_header(width: u32, height: u32) -> Vec<u8> {
+        let mut png = PNG_SIGNATURE.to_vec();
+        png.extend_from_slice(&13u32.to_be_bytes());
+        png.extend_from_slice(b"IHDR");
+        png.extend_from_slice(&width.to_be_bytes());
+        png.extend_from_slice(&height.to_be_bytes());
+        png
+    }
+
+    #[test]
+    fn test_status_responder() {
+        let png = png_header(64, 64);
+        let mut responder = StatusResponder::default()
+            .motd(ChatComponent::text("Down for maintenance"))
+            .version("Maintenance", -1)
+            .echo_protocol(true)
+            .players(0, 20)
+            .favicon_png(&png)
+            .unwrap();
+        assert!(StatusResponder::default()
+            .favicon_png(&png_header(32, 32))
+            .is_err());
+        assert!(StatusResponder::default().favicon_png(b"GIF89a").is_err());
+
+        let handshake = Handshake {
+            protocol_version: 759.into(),
+            ..Default::default()
+        };
+        let body = responder.status(&handshake);
+        assert_eq!(body.version.name, "Maintenance");
+        assert_eq!(body.version.protocol, 759);
+        assert_eq!(body.players.max, 20);
+        assert_eq!(body.favicon_png().unwrap(), Some(png));
+    }
+}
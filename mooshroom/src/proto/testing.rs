@@ -0,0 +1,75 @@
+//! Helpers shared by the tests of this crate and the tools built on it.
+//! Other crates get them with the `test-util` feature.
+
+use std::{
+    io::{Cursor, Read, Write},
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+};
+
+use super::{limbo::Limbo, server::MooshroomServer};
+
+/// A stream that reads from a buffer and collects what is written.
+pub struct MockStream {
+    pub rx: Cursor<Vec<u8>>,
+    pub tx: Vec<u8>,
+}
+
+impl MockStream {
+    pub fn new(rx: Vec<u8>) -> Self {
+        Self {
+            rx: Cursor::new(rx),
+            tx: Vec::new(),
+        }
+    }
+}
+
+impl Read for MockStream {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.rx.read(buf)
+    }
+}
+
+impl Write for MockStream {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.tx.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// A writer whose output can still be read after handing a clone to a
+/// recorder or log.
+#[derive(Clone, Default)]
+pub struct SharedBuffer(pub Arc<Mutex<Vec<u8>>>);
+
+impl SharedBuffer {
+    pub fn bytes(&self) -> Vec<u8> {
+        self.0.lock().unwrap().clone()
+    }
+}
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Runs a limbo on a local port in the background, compressing packets
+/// over 64 bytes and sending no chunks.
+pub fn spawn_limbo_server() -> SocketAddr {
+    let server = MooshroomServer::bind("127.0.0.1:0")
+        .unwrap()
+        .compression_threshold(Some(64));
+    let addr = server.local_addr().unwrap();
+    std::thread::spawn(move || {
+        let _ = Limbo::default().view_distance(0).serve(server);
+    });
+    addr
+}
@@ -0,0 +1,184 @@
+use std::io::{Read, Write};
+
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+use super::{auth::GameProfile, MooshroomProto};
+use crate::{
+    client::login::LoginPluginResponse,
+    core::{
+        error::{MooshroomError, Result},
+        io::{MooshroomReadProto, MooshroomWriteProto, DEFAULT_PROTOCAL_VERSION},
+        primitives::RemainingBytes,
+        varint::VarInt,
+    },
+    server::login::{LoginPluginRequest, Property},
+};
+
+pub const VELOCITY_CHANNEL: &str = "velocity:player_info";
+
+/// The only forwarding version supported: player address and profile, no
+/// chat signing key.
+pub const MODERN_FORWARDING_DEFAULT: i32 = 1;
+
+const PV: usize = DEFAULT_PROTOCAL_VERSION;
+const SIGNATURE_LEN: usize = 32;
+
+/// What a Velocity proxy tells the backend about the player.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ForwardingData {
+    pub address: String,
+    pub profile: GameProfile,
+}
+
+impl ForwardingData {
+    /// Encodes the data and prepends its HMAC-SHA256 signature.
+    pub fn encode(&self, secret: &[u8]) -> Result<Vec<u8>> {
+        let mut payload = Vec::new();
+        VarInt(MODERN_FORWARDING_DEFAULT).write_proto::<PV>(&mut payload)?;
+        self.address.write_proto::<PV>(&mut payload)?;
+        self.profile.uuid.write_proto::<PV>(&mut payload)?;
+        self.profile.name.write_proto::<PV>(&mut payload)?;
+        self.profile.properties.write_proto::<PV>(&mut payload)?;
+
+        let mut buffer = mac(secret)
+            .chain_update(&payload)
+            .finalize()
+            .into_bytes()
+            .to_vec();
+        buffer.extend(payload);
+        Ok(buffer)
+    }
+
+    /// Checks the signature and decodes the data.
+    pub fn decode(secret: &[u8], bytes: &[u8]) -> Result<Self> {
+        if bytes.len() < SIGNATURE_LEN {
+            return Err(forwarding_error("payload too short"));
+        }
+        let (signature, mut payload) = bytes.split_at(SIGNATURE_LEN);
+        mac(secret)
+            .chain_update(payload)
+            .verify_slice(signature)
+            .map_err(|_| forwarding_error("invalid signature"))?;
+
+        let version = VarInt::read_proto::<PV>(&mut payload)?;
+        if version.0 < MODERN_FORWARDING_DEFAULT {
+            return Err(forwarding_error("unsupported version"));
+        }
+        Ok(Self {
+            address: String::read_proto::<PV>(&mut payload)?,
+            profile: GameProfile {
+                uuid: uuid::Uuid::read_proto::<PV>(&mut payload)?,
+                name: String::read_proto::<PV>(&mut payload)?,
+                properties: Vec::<Property>::read_proto::<PV>(&mut payload)?,
+            },
+        })
+    }
+}
+
+fn mac(secret: &[u8]) -> Hmac<Sha256> {
+    Hmac::new_from_slice(secret).expect("HMAC accepts keys of any length")
+}
+
+fn forwarding_error(reason: &str) -> MooshroomError {
+    MooshroomError::AuthenticationFailed(format!("velocity forwarding: {}", reason))
+}
+
+/// A [`LoginPlugins`](super::login_plugin::LoginPlugins) handler that
+/// answers the backend's forwarding request as a Velocity proxy would.
+pub fn forwarding_handler(
+    secret: Vec<u8>,
+    data: ForwardingData,
+) -> impl FnMut(&[u8]) -> Result<Option<Vec<u8>>> + Send + 'static {
+    move |_| data.encode(&secret).map(Some)
+}
+
+/// Backend side: asks the connecting proxy for the player's forwarded
+/// information. Call after reading `LoginStart` and before `LoginSuccess`.
+pub fn request_forwarding<T: Read + Write>(
+    proto: &mut MooshroomProto<T>,
+    secret: &[u8],
+    message_id: i32,
+) -> Result<ForwardingData> {
+    proto.write_packet(&LoginPluginRequest {
+        message_id: message_id.into(),
+        channel: VELOCITY_CHANNEL.into(),
+        data: RemainingBytes(vec![MODERN_FORWARDING_DEFAULT as u8]),
+    })?;
+
+    let response: LoginPluginResponse = proto.read_packet()?;
+    if response.message_id != message_id {
+        return Err(forwarding_error("unexpected message id"));
+    }
+    match response.data {
+        Some(data) => ForwardingData::decode(secret, &data.0),
+        None => Err(forwarding_error("proxy did not forward player info")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{TcpListener, TcpStream};
+
+    use super::*;
+    use crate::{
+        client::login::LoginStart,
+        proto::connection::{LoginOptions, MooshroomConnection, Stage},
+        server::login::LoginSuccess,
+    };
+
+    fn data() -> ForwardingData {
+        ForwardingData {
+            address: "203.0.113.7".into(),
+            profile: GameProfile {
+                uuid: uuid::Uuid::new_v4(),
+                name: "mooshroom".into(),
+                properties: vec![Property {
+                    name: "textures".into(),
+                    value: "e30=".into(),
+                    signature: Some("c2ln".into()),
+                }],
+            },
+        }
+    }
+
+    #[test]
+    fn test_forwarding_signature() {
+        let data = data();
+        let bytes = data.encode(b"secret").unwrap();
+        assert_eq!(ForwardingData::decode(b"secret", &bytes).unwrap(), data);
+        assert!(ForwardingData::decode(b"wrong", &bytes).is_err());
+    }
+
+    #[test]
+    fn test_forwarding_login() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let backend = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut proto = MooshroomProto::new(stream);
+            let _: crate::client::handshake::Handshake = proto.read_packet().unwrap();
+            let _: LoginStart = proto.read_packet().unwrap();
+            let forwarded = request_forwarding(&mut proto, b"secret", 1).unwrap();
+            proto
+                .write_packet(&LoginSuccess::from(forwarded.profile.clone()))
+                .unwrap();
+            forwarded
+        });
+
+        let data = data();
+        let mut conn = MooshroomConnection::with_options(
+            TcpStream::connect(addr).unwrap(),
+            LoginOptions::new(data.profile.name.clone()),
+        );
+        conn.login_plugins().register(
+            VELOCITY_CHANNEL,
+            forwarding_handler(b"secret".to_vec(), data.clone()),
+        );
+        conn.handshake_offline().unwrap();
+
+        assert!(matches!(conn.stage(), Stage::Play(uuid) if *uuid == data.profile.uuid));
+        assert_eq!(backend.join().unwrap(), data);
+    }
+}
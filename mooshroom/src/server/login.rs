@@ -1,11 +1,14 @@
-use mooshroom_core::varint::VarInt;
+use mooshroom_core::{
+    primitives::{Identifier, RemainingBytes},
+    varint::VarInt,
+};
 use mooshroom_macros::{Mooshroom, MooshroomCollection};
 
 use crate::types::Chat;
 
 #[derive(Debug, Clone, Default, Mooshroom)]
 #[packet_id(0)]
-pub struct Disconnect(Chat);
+pub struct Disconnect(pub Chat);
 
 #[derive(Debug, Clone, Default, Mooshroom)]
 #[packet_id(0x01)]
@@ -15,7 +18,7 @@ pub struct EncryptionRequest {
     pub verify_token: Vec<u8>,
 }
 
-#[derive(Debug, Clone, Default, Mooshroom)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Mooshroom)]
 pub struct Property {
     pub name: String,
     pub value: String,
@@ -36,9 +39,19 @@ pub struct SetCompression {
     pub threshold: VarInt,
 }
 
+#[derive(Debug, Clone, Default, Mooshroom)]
+#[packet_id(0x04)]
+pub struct LoginPluginRequest {
+    pub message_id: VarInt,
+    pub channel: Identifier,
+    pub data: RemainingBytes,
+}
+
 #[derive(Debug, Clone, MooshroomCollection)]
 pub enum LoginStage {
     Disconnect(Disconnect),
+    EncryptionRequest(EncryptionRequest),
     SetCompression(SetCompression),
     Success(LoginSuccess),
+    LoginPluginRequest(LoginPluginRequest),
 }
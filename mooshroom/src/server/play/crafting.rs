@@ -7,7 +7,7 @@ use super::nbt;
 #[packet_id(0x10)]
 pub struct CloseContainer(pub u8);
 
-#[derive(Debug, Clone, Default, Mooshroom)]
+#[derive(Debug, Clone, Default, PartialEq, Mooshroom)]
 pub struct SlotData {
     pub item_id: VarInt,
     pub item_count: u8,
@@ -25,6 +25,16 @@ pub struct SetContainerContent {
     pub carried_item: Slot,
 }
 
+#[derive(Debug, Clone, Default, Mooshroom)]
+#[packet_id(0x13)]
+pub struct SetContainerSlot {
+    /// -1 sets the carried item, 0 always means the player inventory.
+    pub window_id: i8,
+    pub state_id: VarInt,
+    pub slot: i16,
+    pub slot_data: Slot,
+}
+
 #[derive(Debug, Clone, Default, Mooshroom)]
 #[packet_id(0x1D)]
 pub struct OpenHorseScreen {
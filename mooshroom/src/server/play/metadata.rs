@@ -1,12 +1,12 @@
 use mooshroom_core::{
     io::{MooshroomReadProto, MooshroomReadable, MooshroomWritable, MooshroomWriteProto},
-    primitives::Identifier,
+    primitives::{Identifier, RemainingBytes},
     varint::VarInt,
 };
 use mooshroom_macros::Mooshroom;
 
 use super::crafting::Slot;
-use crate::core::error::Result;
+use crate::{core::error::Result, types::Chat};
 
 pub type Ingredient = Vec<Slot>;
 pub type Ingredients = Vec<Ingredient>;
@@ -31,12 +31,25 @@ pub struct Commands {
 #[packet_id(0x16)]
 pub struct PluginMessage {
     pub channel: Identifier,
-    pub data: Vec<u8>,
+    pub data: RemainingBytes,
 }
 #[derive(Debug, Clone, Default, Mooshroom)]
 #[packet_id(0x20)]
 pub struct KeepAlive(pub i64);
 
+#[derive(Debug, Clone, Default, Mooshroom)]
+#[packet_id(0x2F)]
+pub struct Ping(pub i32);
+
+#[derive(Debug, Clone, Default, Mooshroom)]
+#[packet_id(0x3D)]
+pub struct ResourcePack {
+    pub url: String,
+    pub hash: String,
+    pub forced: bool,
+    pub prompt_message: Option<Chat>,
+}
+
 #[derive(Debug, Clone, Default, Mooshroom)]
 #[packet_id(0x42)]
 pub struct ServerData {
@@ -19,8 +19,8 @@ pub enum PlayStage {
     EntityAnimation(population::EntityAnimation),
     //#[id(0x04)]
     //AwardStatistics(player::AwardStatistics),
-    //#[id(0x05)]
-    //AcknowledgeBlockChange(world::AcknowledgeBlockChange),
+    #[id(0x05)]
+    AcknowledgeBlockChange(world::AcknowledgeBlockChange),
     //#[id(0x06)]
     //SetBlockDestroyStage(world::SetBlockDestroyStage),
     //#[id(0x07)]
@@ -47,8 +47,8 @@ pub enum PlayStage {
     SetContainerContent(crafting::SetContainerContent),
     // #[id(0x12)]
     // SetContainerProperty(crafting::SetContainerProperty),
-    // #[id(0x13)]
-    // SetContainerSlot(crafting::SetContainerSlot),
+    #[id(0x13)]
+    SetContainerSlot(crafting::SetContainerSlot),
     // #[id(0x14)]
     // SetCooldown(crafting::SetCooldown),
     // #[id(0x15)]
@@ -105,8 +105,8 @@ pub enum PlayStage {
     //OpenScreen(crafting::OpenScreen),
     //#[id(0x2E)]
     //OpenSignEditor(crafting::OpenSignEditor),
-    //#[id(0x2F)]
-    //Ping(metadata::Ping),
+    #[id(0x2F)]
+    Ping(metadata::Ping),
     //#[id(0x30)]
     //PlaceGhostRecipe(crafting::PlaceGhostRecipe),
     #[id(0x31)]
@@ -133,8 +133,8 @@ pub enum PlayStage {
     RemoveEntities(population::RemoveEntities),
     //#[id(0x3C)]
     //RemoveEntityEffect(population::RemoveEntityEffect),
-    //#[id(0x3D)]
-    //ResourcePack(metadata::ResourcePack),
+    #[id(0x3D)]
+    ResourcePack(metadata::ResourcePack),
     #[id(0x3E)]
     Respawn(player::Respawn),
     #[id(0x3F)]
@@ -1,19 +1,28 @@
 //TODO: clean up code
-use cesu8::from_cesu8;
+use cesu8::{from_cesu8, to_cesu8};
 use mooshroom_core::{
     error::MooshroomError,
     io::{
         MooshroomReadProto,
         MooshroomReadable,
         MooshroomWritable,
+        MooshroomWriteProto,
         Protocal,
         DEFAULT_PROTOCAL_VERSION,
     },
+    primitives::read_bytes,
 };
 
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, PartialEq)]
 pub struct NptCompound(NptNamedTag<DEFAULT_PROTOCAL_VERSION>);
 
+impl NptCompound {
+    /// A root compound with an empty name, as sent in packets.
+    pub fn new(tags: Vec<NptNamedTag<DEFAULT_PROTOCAL_VERSION>>) -> Self {
+        Self(NptNamedTag(String::new(), NptTagData::Compount(tags)))
+    }
+}
+
 impl<const PV: Protocal> MooshroomReadable<PV> for NptCompound {
     fn read(reader: &mut impl std::io::Read) -> mooshroom_core::error::Result<Self> {
         let d = NptTagData::read_compound_body(reader)?;
@@ -22,12 +31,12 @@ impl<const PV: Protocal> MooshroomReadable<PV> for NptCompound {
 }
 
 impl<const PV: Protocal> MooshroomWritable<PV> for NptCompound {
-    fn write(&self, _: &mut impl std::io::Write) -> mooshroom_core::error::Result<()> {
-        todo!()
+    fn write(&self, writer: &mut impl std::io::Write) -> mooshroom_core::error::Result<()> {
+        self.0.write_body(writer)
     }
 }
 
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, PartialEq)]
 pub struct NptTagDataDefault(NptTagData<DEFAULT_PROTOCAL_VERSION>);
 
 impl<const PV: Protocal> MooshroomReadable<PV> for NptTagDataDefault {
@@ -38,15 +47,30 @@ impl<const PV: Protocal> MooshroomReadable<PV> for NptTagDataDefault {
 }
 
 impl<const PV: Protocal> MooshroomWritable<PV> for NptTagDataDefault {
-    fn write(&self, _: &mut impl std::io::Write) -> mooshroom_core::error::Result<()> {
-        todo!()
+    fn write(&self, writer: &mut impl std::io::Write) -> mooshroom_core::error::Result<()> {
+        self.0.write(writer)
     }
 }
 
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, PartialEq)]
 pub struct NptNamedTag<const PV: Protocal>(String, NptTagData<PV>);
 
-#[derive(Debug, Clone, Default)]
+impl<const PV: Protocal> NptNamedTag<PV> {
+    pub fn new(name: impl Into<String>, data: NptTagData<PV>) -> Self {
+        Self(name.into(), data)
+    }
+
+    fn write_body(&self, writer: &mut impl std::io::Write) -> mooshroom_core::error::Result<()> {
+        self.1.type_id().write_proto::<PV>(writer)?;
+        if !matches!(self.1, NptTagData::End) {
+            NptTagData::<PV>::write_string(&self.0, writer)?;
+            self.1.write_payload(writer)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
 pub enum NptTagData<const PV: Protocal> {
     #[default]
     End,
@@ -72,8 +96,9 @@ impl<const PV: Protocal> MooshroomReadable<PV> for NptTagData<PV> {
 }
 
 impl<const PV: Protocal> MooshroomWritable<PV> for NptTagData<PV> {
-    fn write(&self, _: &mut impl std::io::Write) -> mooshroom_core::error::Result<()> {
-        todo!()
+    fn write(&self, writer: &mut impl std::io::Write) -> mooshroom_core::error::Result<()> {
+        self.type_id().write_proto::<PV>(writer)?;
+        self.write_payload(writer)
     }
 }
 
@@ -93,20 +118,17 @@ impl<const PV: Protocal> NptTagData<PV> {
             10 => Self::read_compound(reader)?,
             11 => Self::IntArray(Self::read_array(reader)?),
             12 => Self::LongArray(Self::read_array(reader)?),
-            _ => unimplemented!(),
+            ty => return Err(MooshroomError::InvalidNbtTag(ty)),
         };
         Ok(r)
     }
     fn read_byte_array(reader: &mut impl std::io::Read) -> mooshroom_core::error::Result<Vec<u8>> {
         let len = <i32 as MooshroomReadable<PV>>::read(reader)?;
-        let mut buffer = vec![0; len as usize];
-        reader.read_exact(&mut buffer[..])?;
-        Ok(buffer)
+        read_bytes(reader, len)
     }
     fn read_string(reader: &mut impl std::io::Read) -> mooshroom_core::error::Result<String> {
         let len = u16::read_proto::<PV>(reader)?;
-        let mut buffer = vec![0; len as usize];
-        reader.read_exact(&mut buffer[..])?;
+        let buffer = read_bytes(reader, len as i32)?;
         let s = from_cesu8(&buffer).map_err(|_| MooshroomError::InvalidNbtTag(8))?;
         Ok(s.into_owned())
     }
@@ -115,7 +137,12 @@ impl<const PV: Protocal> NptTagData<PV> {
     ) -> mooshroom_core::error::Result<Vec<NptTagData<PV>>> {
         let ty = <u8 as MooshroomReadable<PV>>::read(reader)?;
         let len = <u32 as MooshroomReadable<PV>>::read(reader)?;
-        let mut items = Vec::with_capacity(len as usize);
+        // End tags have no payload, so a list of them could be any length
+        // without running out of data.
+        if ty == 0 {
+            return Ok(Vec::new());
+        }
+        let mut items = Vec::with_capacity((len as usize).min(1024));
         for _ in 0..len {
             items.push(Self::read_type(ty, reader)?);
         }
@@ -148,10 +175,114 @@ impl<const PV: Protocal> NptTagData<PV> {
         reader: &mut impl std::io::Read,
     ) -> mooshroom_core::error::Result<Vec<D>> {
         let len = <i32 as MooshroomReadable<PV>>::read(reader)?;
-        let mut items = Vec::with_capacity(len as usize);
+        if len < 0 {
+            return Err(MooshroomError::InvalidLength(len));
+        }
+        let mut items = Vec::with_capacity((len as usize).min(1024));
         for _ in 0..len {
             items.push(D::read(reader)?);
         }
         Ok(items)
     }
+
+    fn type_id(&self) -> u8 {
+        match self {
+            Self::End => 0,
+            Self::Byte(_) => 1,
+            Self::Short(_) => 2,
+            Self::Int(_) => 3,
+            Self::Long(_) => 4,
+            Self::Float(_) => 5,
+            Self::Double(_) => 6,
+            Self::ByteArray(_) => 7,
+            Self::String(_) => 8,
+            Self::List(_) => 9,
+            Self::Compount(_) => 10,
+            Self::IntArray(_) => 11,
+            Self::LongArray(_) => 12,
+        }
+    }
+    fn write_payload(&self, writer: &mut impl std::io::Write) -> mooshroom_core::error::Result<()> {
+        match self {
+            Self::End => {}
+            Self::Byte(v) => v.write_proto::<PV>(writer)?,
+            Self::Short(v) => v.write_proto::<PV>(writer)?,
+            Self::Int(v) => v.write_proto::<PV>(writer)?,
+            Self::Long(v) => v.write_proto::<PV>(writer)?,
+            Self::Float(v) => v.write_proto::<PV>(writer)?,
+            Self::Double(v) => v.write_proto::<PV>(writer)?,
+            Self::ByteArray(v) => {
+                (v.len() as i32).write_proto::<PV>(writer)?;
+                writer.write_all(v)?;
+            }
+            Self::String(v) => Self::write_string(v, writer)?,
+            Self::List(items) => {
+                // Lists are homogeneous, an empty one is written as a list of End tags.
+                let ty = items.first().map_or(0, Self::type_id);
+                ty.write_proto::<PV>(writer)?;
+                (items.len() as u32).write_proto::<PV>(writer)?;
+                for item in items {
+                    item.write_payload(writer)?;
+                }
+            }
+            Self::Compount(items) => {
+                for item in items {
+                    item.write_body(writer)?;
+                }
+                0u8.write_proto::<PV>(writer)?;
+            }
+            Self::IntArray(v) => Self::write_array(v, writer)?,
+            Self::LongArray(v) => Self::write_array(v, writer)?,
+        }
+        Ok(())
+    }
+    fn write_string(
+        s: &str,
+        writer: &mut impl std::io::Write,
+    ) -> mooshroom_core::error::Result<()> {
+        let bytes = to_cesu8(s);
+        (bytes.len() as u16).write_proto::<PV>(writer)?;
+        writer.write_all(&bytes)?;
+        Ok(())
+    }
+    fn write_array<D: MooshroomWritable<PV>>(
+        items: &[D],
+        writer: &mut impl std::io::Write,
+    ) -> mooshroom_core::error::Result<()> {
+        (items.len() as i32).write_proto::<PV>(writer)?;
+        for item in items {
+            item.write(writer)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_round_trip() {
+        let tag = NptCompound(NptNamedTag(
+            "".into(),
+            NptTagData::Compount(vec![
+                NptNamedTag("Damage".into(), NptTagData::Int(3)),
+                NptNamedTag(
+                    "Lore".into(),
+                    NptTagData::List(vec![NptTagData::String("Shiny".into())]),
+                ),
+                NptNamedTag("Ids".into(), NptTagData::LongArray(vec![1, -1])),
+            ]),
+        ));
+        let mut buf = Vec::new();
+        MooshroomWritable::<DEFAULT_PROTOCAL_VERSION>::write(&tag, &mut buf).unwrap();
+        let read: NptCompound =
+            MooshroomReadable::<DEFAULT_PROTOCAL_VERSION>::read(&mut &buf[..]).unwrap();
+        assert_eq!(read, tag);
+
+        buf.clear();
+        MooshroomWritable::<DEFAULT_PROTOCAL_VERSION>::write(&NptCompound::default(), &mut buf)
+            .unwrap();
+        assert_eq!(buf, [0]);
+    }
 }
@@ -8,45 +8,12 @@ use mooshroom_core::{
 };
 use mooshroom_macros::{Mooshroom, MooshroomBitfield};
 
-use super::{crafting::Slot, world::Angle};
+use super::crafting::Slot;
 use crate::types::Chat;
 
-#[derive(Debug, Clone, Default, Mooshroom)]
-#[packet_id(0x16)]
-pub struct SetPlayerRotation {
-    pub yaw: f32,
-    pub pitch: f32,
-    pub on_ground: bool,
-}
-
 #[derive(Debug, Clone, Default, Mooshroom)]
 #[packet_id(0x19)]
-pub struct Disconnect(Chat);
-
-#[derive(Debug, Clone, Default, Mooshroom)]
-#[repr(u8)]
-#[value_type(VarInt)]
-pub enum PlayerAction {
-    #[default]
-    StartSneeking=0,
-    StopSneeking=1,
-    LeaveBed=2,
-    StartSprinting=3,
-    StopSprinting=4,
-    StartJumpWithHorse=5,
-    StopJumpWithHorse=6,
-    OpenHorseInventory=7,
-    StartFLyingWithElytra=8
-}
-//
-//#[derive(Debug, Clone, Default, Mooshroom)]
-//#[packet_id(0x1d)]
-//pub struct PlayerCommand{
-//    pub entity_id: VarInt,
-//    pub action: PlayerAction,
-//    pub jump_boost: VarInt
-//}
-
+pub struct Disconnect(pub Chat);
 
 #[derive(Debug, Clone, Default, MooshroomBitfield)]
 #[value_type(u8)]
@@ -144,7 +111,8 @@ pub struct SetHeldItem {
 #[packet_id(0x4D)]
 pub struct SetDefaultSpawnPosition {
     pub location: Position,
-    pub angle: Angle,
+    /// In degrees. Unlike entity angles, this is a float on the wire.
+    pub angle: f32,
 }
 
 #[derive(Debug, Clone, Default, Mooshroom)]
@@ -157,17 +157,17 @@ pub enum PlayerAction {
     #[id(3)]
     UpdateDisplayName(Vec<ActionFor<Option<String>>>),
     #[id(4)]
-    RemovePlayer(()),
+    RemovePlayer(Vec<uuid::Uuid>),
 }
 
 impl Default for PlayerAction {
     fn default() -> Self {
-        Self::RemovePlayer(())
+        Self::RemovePlayer(Vec::new())
     }
 }
 
 #[derive(Debug, Clone, Default)]
-pub struct PlayerInfo(PlayerAction);
+pub struct PlayerInfo(pub PlayerAction);
 impl<const PV: usize> MooshroomPacket<PV> for PlayerInfo {
     const PACKET_ID: VarInt = VarInt(0x37);
 }
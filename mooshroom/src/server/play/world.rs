@@ -7,6 +7,7 @@ use mooshroom_macros::{Mooshroom, MooshroomBitfield};
 use super::{nbt, population::WorldPosition};
 use crate::{
     core::primitives::{Identifier, Position, Vec3},
+    shared::PreviousMessage,
     types::Chat,
 };
 
@@ -32,6 +33,13 @@ pub struct ChangeDifficulty {
     pub difficulty_locked: bool,
 }
 
+/// Confirms the client's block changes up to this sequence number.
+#[derive(Debug, Clone, Default, Mooshroom)]
+#[packet_id(0x05)]
+pub struct AcknowledgeBlockChange {
+    pub sequence_id: VarInt,
+}
+
 #[derive(Debug, Clone, Default, Mooshroom)]
 #[packet_id(0x09)]
 pub struct BlockUpdate {
@@ -139,18 +147,18 @@ pub struct BlockEntity {
     pub data: nbt::NptCompound,
 }
 pub type BitSet = Vec<u64>;
-// type LightArray = [u8;2048];
 
+/// Light for a column of sections, including one below and one above the
+/// world. Each array holds 2048 bytes, a nibble per block.
 #[derive(Debug, Clone, Default, Mooshroom)]
 pub struct LightingData {
     pub trust_edges: bool,
-    //pub sky_light_mask: BitSet,
-    //pub block_light_mask: BitSet,
-    //pub empty_sky_mask: BitSet,
-    //pub empty_block_mask: BitSet,
-    //pub sky_light_arrays: Vec<LightArray>,
-    //pub block_light_n: VarLong,
-    //pub block_light_arrays: Vec<LightArray>,
+    pub sky_light_mask: BitSet,
+    pub block_light_mask: BitSet,
+    pub empty_sky_light_mask: BitSet,
+    pub empty_block_light_mask: BitSet,
+    pub sky_light_arrays: Vec<Vec<u8>>,
+    pub block_light_arrays: Vec<Vec<u8>>,
 }
 #[derive(Debug, Clone, Default, Mooshroom)]
 #[packet_id(0x21)]
@@ -224,12 +232,6 @@ pub struct LoginPlay {
     pub death_location: Option<DeathLocation>,
 }
 
-#[derive(Debug, Clone, Default, Mooshroom)]
-pub struct PreviousMessage {
-    pub sender: uuid::Uuid,
-    pub signature: Vec<u8>,
-}
-
 #[derive(Debug, Clone, Default, Mooshroom)]
 #[repr(i32)]
 #[value_type(VarInt)]
@@ -243,9 +245,11 @@ pub enum FilterType {
 #[derive(Debug, Clone, Default, Mooshroom)]
 #[packet_id(0x33)]
 pub struct PlayerChatMessage {
+    /// The header signature of the sender's previous message.
     pub message_signature: Option<Vec<u8>>,
     pub sender: uuid::Uuid,
-    pub header_signature: Option<Vec<u8>>,
+    /// Empty when the message is unsigned.
+    pub header_signature: Vec<u8>,
     pub plain_message: String,
     pub formatted_message: Option<Chat>,
     pub timestamp: i64,
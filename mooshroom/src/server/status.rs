@@ -1,7 +1,11 @@
-use mooshroom_macros::Mooshroom;
+use base64::Engine;
+use mooshroom_core::error::{MooshroomError, Result};
+use mooshroom_macros::{Mooshroom, MooshroomCollection};
 use serde::{Deserialize, Serialize};
 
-use crate::containers::Json;
+use crate::{containers::Json, types::ChatComponent};
+
+const FAVICON_PREFIX: &str = "data:image/png;base64,";
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct ServerVersion {
@@ -19,19 +23,52 @@ pub struct ServerPlayer {
 pub struct ServerPlayers {
     pub max: usize,
     pub online: usize,
-    pub sample: Vec<ServerPlayers>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sample: Vec<ServerPlayer>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct StatusBody {
     pub version: ServerVersion,
-    pub favicon: String,
+    #[serde(default)]
+    pub players: ServerPlayers,
+    #[serde(default)]
+    pub description: ChatComponent,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub favicon: Option<String>,
     #[serde(rename = "previewsChat")]
     pub previews_chat: Option<bool>,
     #[serde(rename = "enforcesSecureChat")]
     pub enforces_secure_chat: Option<bool>,
 }
 
+impl StatusBody {
+    /// Decodes the `data:image/png;base64,...` favicon into PNG bytes.
+    pub fn favicon_png(&self) -> Result<Option<Vec<u8>>> {
+        let favicon = match &self.favicon {
+            Some(f) => f,
+            None => return Ok(None),
+        };
+        let data = favicon
+            .strip_prefix(FAVICON_PREFIX)
+            .ok_or_else(|| MooshroomError::InvalidFavicon("not a png data uri".into()))?;
+        // Some servers wrap the base64 text.
+        let data: String = data.split_whitespace().collect();
+        base64::engine::general_purpose::STANDARD
+            .decode(data)
+            .map(Some)
+            .map_err(|e| MooshroomError::InvalidFavicon(e.to_string()))
+    }
+
+    pub fn set_favicon_png(&mut self, png: &[u8]) {
+        self.favicon = Some(format!(
+            "{}{}",
+            FAVICON_PREFIX,
+            base64::engine::general_purpose::STANDARD.encode(png)
+        ));
+    }
+}
+
 #[derive(Debug, Clone, Default, Mooshroom)]
 #[packet_id(0x00)]
 pub struct StatusResponse {
@@ -40,4 +77,10 @@ pub struct StatusResponse {
 
 #[derive(Debug, Clone, Default, Mooshroom)]
 #[packet_id(0x01)]
-pub struct PingResponse(u64);
+pub struct PingResponse(pub u64);
+
+#[derive(Debug, Clone, MooshroomCollection)]
+pub enum StatusStage {
+    Response(StatusResponse),
+    Pong(PingResponse),
+}
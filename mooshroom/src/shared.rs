@@ -6,3 +6,10 @@ pub struct SignatureData {
     pub public_key: Vec<u8>,
     pub signature: Vec<u8>,
 }
+
+/// A chat message the client has seen, identified by its header signature.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Mooshroom)]
+pub struct PreviousMessage {
+    pub sender: uuid::Uuid,
+    pub signature: Vec<u8>,
+}
@@ -1 +1,109 @@
+use serde::{Deserialize, Deserializer, Serialize};
+
 pub type Chat = String;
+
+/// A JSON text component, as used for the server list MOTD. Plain strings and
+/// arrays are accepted too and normalised into this form.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ChatComponent {
+    pub text: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub translate: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bold: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub italic: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub underlined: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub strikethrough: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub obfuscated: Option<bool>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub extra: Vec<ChatComponent>,
+}
+
+impl ChatComponent {
+    pub fn text(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            ..Default::default()
+        }
+    }
+
+    /// The text of this component and its children, without formatting.
+    pub fn to_plain(&self) -> String {
+        let mut s = self.text.clone();
+        for e in &self.extra {
+            s.push_str(&e.to_plain());
+        }
+        s
+    }
+}
+
+impl<'de> Deserialize<'de> for ChatComponent {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize, Default)]
+        #[serde(default)]
+        struct Object {
+            text: String,
+            translate: Option<String>,
+            color: Option<String>,
+            bold: Option<bool>,
+            italic: Option<bool>,
+            underlined: Option<bool>,
+            strikethrough: Option<bool>,
+            obfuscated: Option<bool>,
+            extra: Vec<ChatComponent>,
+        }
+
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Repr {
+            Text(String),
+            List(Vec<ChatComponent>),
+            Object(Object),
+        }
+
+        Ok(match Repr::deserialize(deserializer)? {
+            Repr::Text(text) => Self::text(text),
+            Repr::List(list) => {
+                let mut list = list.into_iter();
+                let mut first = list.next().unwrap_or_default();
+                first.extra.extend(list);
+                first
+            }
+            Repr::Object(o) => Self {
+                text: o.text,
+                translate: o.translate,
+                color: o.color,
+                bold: o.bold,
+                italic: o.italic,
+                underlined: o.underlined,
+                strikethrough: o.strikethrough,
+                obfuscated: o.obfuscated,
+                extra: o.extra,
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chat_component_forms() {
+        let plain: ChatComponent = serde_json::from_str(r#""A Minecraft Server""#).unwrap();
+        assert_eq!(plain, ChatComponent::text("A Minecraft Server"));
+
+        let nested: ChatComponent = serde_json::from_str(
+            r#"{"text":"","extra":[{"text":"Hello ","bold":true},"world",["!"]]}"#,
+        )
+        .unwrap();
+        assert_eq!(nested.to_plain(), "Hello world!");
+        assert_eq!(nested.extra[0].bold, Some(true));
+    }
+}